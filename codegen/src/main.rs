@@ -0,0 +1,627 @@
+// Generates typed yew-query hooks from an OpenAPI 3 (JSON) spec
+//
+// usage: yew-query-codegen <openapi.json> > src/api.rs
+//
+// the generated module depends on `yew-query`, `reqwasm`, `serde`,
+// `serde_json` and `js-sys`
+
+use serde_json::{Map, Value};
+use std::fmt::Write;
+use std::{env, fs, process};
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+struct Param {
+    name: String,
+    field: String,
+    ty: String,
+    required: bool,
+    in_path: bool,
+    // sent as one `name=value` pair per item, or comma separated in the path
+    array: bool,
+    // `$ref` types are generated without `Default`
+    has_default: bool,
+}
+
+struct Operation {
+    id: String,
+    method: String,
+    path: String,
+    params: Vec<Param>,
+    body: Option<String>,
+    response: String,
+}
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+fn snake_case(name: &str) -> String {
+    let name = words(name).join("_");
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else if name.starts_with(|c: char| c.is_numeric()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn ref_name(reference: &str) -> String {
+    pascal_case(reference.rsplit('/').next().unwrap_or(reference))
+}
+
+fn rust_type(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return ref_name(reference);
+    }
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => "String".into(),
+        Some("integer") => match schema.get("format").and_then(Value::as_str) {
+            Some("int32") => "i32".into(),
+            _ => "i64".into(),
+        },
+        Some("number") => match schema.get("format").and_then(Value::as_str) {
+            Some("float") => "f32".into(),
+            _ => "f64".into(),
+        },
+        Some("boolean") => "bool".into(),
+        Some("array") => format!(
+            "Vec<{}>",
+            schema
+                .get("items")
+                .map(rust_type)
+                .unwrap_or_else(|| "serde_json::Value".into())
+        ),
+        _ => "serde_json::Value".into(),
+    }
+}
+
+fn json_schema(content: Option<&Value>) -> Option<&Value> {
+    content?
+        .get("content")?
+        .get("application/json")?
+        .get("schema")
+}
+
+fn parse_operation(path: &str, method: &str, operation: &Value) -> Operation {
+    let id = operation
+        .get("operationId")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .unwrap_or_else(|| format!("{} {}", method, path));
+
+    let params = operation
+        .get("parameters")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|param| {
+            let name = param.get("name")?.as_str()?;
+            let location = param.get("in")?.as_str()?;
+            if location != "path" && location != "query" {
+                return None;
+            }
+            let schema = param.get("schema");
+            let required = location == "path"
+                || param
+                    .get("required")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+
+            Some(Param {
+                name: name.into(),
+                field: snake_case(name),
+                ty: schema.map(rust_type).unwrap_or_else(|| "String".into()),
+                required,
+                in_path: location == "path",
+                array: schema.and_then(|schema| schema.get("type")) == Some(&"array".into()),
+                has_default: !required || schema.and_then(|schema| schema.get("$ref")).is_none(),
+            })
+        })
+        .collect();
+
+    let responses = operation.get("responses");
+    let response = ["200", "201"]
+        .iter()
+        .find_map(|status| json_schema(responses?.get(*status)))
+        .map(rust_type)
+        .unwrap_or_else(|| "()".into());
+
+    Operation {
+        id,
+        method: method.into(),
+        path: path.into(),
+        params,
+        body: json_schema(operation.get("requestBody")).map(rust_type),
+        response,
+    }
+}
+
+fn write_struct(out: &mut String, name: &str, schema: &Value) {
+    let empty = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    writeln!(
+        out,
+        "#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]"
+    )
+    .unwrap();
+    writeln!(out, "pub struct {} {{", pascal_case(name)).unwrap();
+    for (property, schema) in properties {
+        let field = snake_case(property);
+        if field.trim_start_matches("r#") != property {
+            writeln!(out, "    #[serde(rename = \"{}\")]", property).unwrap();
+        }
+        if required.contains(&property.as_str()) {
+            writeln!(out, "    pub {}: {},", field, rust_type(schema)).unwrap();
+        } else {
+            writeln!(
+                out,
+                "    #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+            )
+            .unwrap();
+            writeln!(out, "    pub {}: Option<{}>,", field, rust_type(schema)).unwrap();
+        }
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_params(out: &mut String, op: &Operation) {
+    let name = format!("{}Params", pascal_case(&op.id));
+
+    if op.params.iter().all(|param| param.has_default) {
+        writeln!(out, "#[derive(Clone, PartialEq, Debug, Default)]").unwrap();
+    } else {
+        writeln!(out, "#[derive(Clone, PartialEq, Debug)]").unwrap();
+    }
+    writeln!(out, "pub struct {} {{", name).unwrap();
+    for param in &op.params {
+        if param.required {
+            writeln!(out, "    pub {}: {},", param.field, param.ty).unwrap();
+        } else {
+            writeln!(out, "    pub {}: Option<{}>,", param.field, param.ty).unwrap();
+        }
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    // the url uniquely identifies the request, so it doubles as the query key
+    let mut path = op.path.clone();
+    let mut args = vec![];
+    for param in op.params.iter().filter(|param| param.in_path) {
+        path = path.replace(&format!("{{{}}}", param.name), "{}");
+        if param.array {
+            args.push(format!(
+                "self.{}.iter().map(encode).collect::<Vec<_>>().join(\",\")",
+                param.field
+            ));
+        } else {
+            args.push(format!("encode(&self.{})", param.field));
+        }
+    }
+
+    let query = op
+        .params
+        .iter()
+        .filter(|param| !param.in_path)
+        .collect::<Vec<_>>();
+
+    writeln!(out, "impl {} {{", name).unwrap();
+    writeln!(out, "    pub fn url(&self) -> String {{").unwrap();
+    writeln!(
+        out,
+        "        let {}url = format!(\"{{}}{}\", BASE_URL{});",
+        if query.is_empty() { "" } else { "mut " },
+        path,
+        args.iter()
+            .map(|arg| format!(", {}", arg))
+            .collect::<String>()
+    )
+    .unwrap();
+
+    if !query.is_empty() {
+        writeln!(out, "        let mut query = vec![];").unwrap();
+        for param in query {
+            let push = format!(
+                "query.push(format!(\"{}={{}}\", encode(value)));",
+                param.name
+            );
+            let indent = if param.required {
+                writeln!(out, "        let value = &self.{};", param.field).unwrap();
+                "        "
+            } else {
+                writeln!(out, "        if let Some(value) = &self.{} {{", param.field).unwrap();
+                "            "
+            };
+            if param.array {
+                writeln!(out, "{}for value in value {{", indent).unwrap();
+                writeln!(out, "{}    {}", indent, push).unwrap();
+                writeln!(out, "{}}}", indent).unwrap();
+            } else {
+                writeln!(out, "{}{}", indent, push).unwrap();
+            }
+            if !param.required {
+                writeln!(out, "        }}").unwrap();
+            }
+        }
+        writeln!(out, "        if !query.is_empty() {{").unwrap();
+        writeln!(out, "            url.push('?');").unwrap();
+        writeln!(out, "            url.push_str(&query.join(\"&\"));").unwrap();
+        writeln!(out, "        }}").unwrap();
+    }
+
+    writeln!(out, "        url").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_request(out: &mut String, op: &Operation) {
+    let name = snake_case(&op.id);
+    let mut args = vec![format!("params: {}Params", pascal_case(&op.id))];
+    if let Some(body) = &op.body {
+        args.push(format!("body: {}", body));
+    }
+
+    writeln!(
+        out,
+        "pub async fn {}({}) -> Result<{}, String> {{",
+        name,
+        args.join(", "),
+        op.response
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    let request = Request::{}(&params.url());",
+        op.method
+    )
+    .unwrap();
+    if op.body.is_some() {
+        writeln!(
+            out,
+            "    let request = request\n        .header(\"Content-Type\", \"application/json\")\n        .body(serde_json::to_string(&body).map_err(|err| err.to_string())?);"
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "    let response = request.send().await.map_err(|err| err.to_string())?;"
+    )
+    .unwrap();
+    writeln!(out, "    if !response.ok() {{").unwrap();
+    writeln!(
+        out,
+        "        return Err(format!(\"{{}} {{}}\", response.status(), response.status_text()));"
+    )
+    .unwrap();
+    writeln!(out, "    }}").unwrap();
+    if op.response == "()" {
+        writeln!(out, "    Ok(())").unwrap();
+    } else {
+        writeln!(
+            out,
+            "    response.json().await.map_err(|err| err.to_string())"
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_query_hook(out: &mut String, op: &Operation) {
+    let name = snake_case(&op.id);
+    let params = format!("{}Params", pascal_case(&op.id));
+
+    writeln!(
+        out,
+        "pub fn use_{}(params: {}, options: QueryOptions<{}>) -> QueryState<{}> {{",
+        name.trim_start_matches("r#"),
+        params,
+        op.response,
+        op.response
+    )
+    .unwrap();
+    writeln!(out, "    let query_key = params.url();").unwrap();
+    writeln!(out, "    use_query(").unwrap();
+    writeln!(out, "        &query_key,").unwrap();
+    writeln!(out, "        move |_| {{").unwrap();
+    writeln!(out, "            let params = params.clone();").unwrap();
+    writeln!(
+        out,
+        "            Box::pin(async move {{ {}(params).await }})",
+        name
+    )
+    .unwrap();
+    writeln!(out, "        }},").unwrap();
+    // generated types are all `PartialEq`
+    writeln!(out, "        options.structural_sharing(),").unwrap();
+    writeln!(out, "    )").unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_mutation_hook(out: &mut String, op: &Operation) {
+    let name = snake_case(&op.id);
+    let params = format!("{}Params", pascal_case(&op.id));
+    let (arg, call) = match &op.body {
+        Some(body) => (
+            format!("({}, {})", params, body),
+            format!(
+                "|(params, body): ({}, {})| Box::pin({}(params, body))",
+                params, body, name
+            ),
+        ),
+        None => (
+            params.clone(),
+            format!("|params: {}| Box::pin({}(params))", params, name),
+        ),
+    };
+
+    writeln!(
+        out,
+        "pub fn use_{}(\n    options: MutationOptions<{}, String, {}>,\n) -> UseMutationHandle<{}, {}, String> {{",
+        name.trim_start_matches("r#"),
+        op.response,
+        arg,
+        arg,
+        op.response
+    )
+    .unwrap();
+    writeln!(out, "    use_mutation({}, options)", call).unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn generate(spec: &Value) -> Result<String, String> {
+    let paths = spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .ok_or("spec has no `paths`")?;
+    let base_url = spec
+        .pointer("/servers/0/url")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .trim_end_matches('/');
+
+    let mut operations = vec![];
+    for (path, item) in paths {
+        for method in METHODS {
+            if let Some(operation) = item.get(method) {
+                operations.push(parse_operation(path, method, operation));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    if let Some(title) = spec.pointer("/info/title").and_then(Value::as_str) {
+        writeln!(
+            out,
+            "// generated by yew-query-codegen from \"{}\", do not edit\n",
+            title
+        )
+        .unwrap();
+    }
+    writeln!(out, "#![allow(dead_code, clippy::all)]\n").unwrap();
+    writeln!(out, "use reqwasm::http::Request;").unwrap();
+    writeln!(out, "use serde::{{Deserialize, Serialize}};").unwrap();
+    writeln!(
+        out,
+        "use yew_query::{{\n    use_mutation, use_query, MutationOptions, QueryOptions, QueryState, UseMutationHandle,\n}};\n"
+    )
+    .unwrap();
+    writeln!(out, "pub const BASE_URL: &str = \"{}\";\n", base_url).unwrap();
+    writeln!(
+        out,
+        "fn encode(value: &impl ToString) -> String {{\n    String::from(js_sys::encode_uri_component(&value.to_string()))\n}}\n"
+    )
+    .unwrap();
+
+    if let Some(schemas) = spec
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+    {
+        for (name, schema) in schemas {
+            if schema.get("properties").is_some() {
+                write_struct(&mut out, name, schema);
+            } else {
+                writeln!(
+                    out,
+                    "pub type {} = {};\n",
+                    pascal_case(name),
+                    rust_type(schema)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    for op in &operations {
+        write_params(&mut out, op);
+        write_request(&mut out, op);
+        if op.method == "get" {
+            write_query_hook(&mut out, op);
+        } else {
+            write_mutation_hook(&mut out, op);
+        }
+    }
+
+    Ok(out)
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: yew-query-codegen <openapi.json>");
+            process::exit(2);
+        }
+    };
+
+    let result = fs::read_to_string(&path)
+        .map_err(|err| format!("couldn't read {}: {}", path, err))
+        .and_then(|spec| serde_json::from_str(&spec).map_err(|err| err.to_string()))
+        .and_then(|spec| generate(&spec));
+
+    match result {
+        Ok(out) => print!("{}", out),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(parameters: Value) -> Value {
+        json!({
+            "info": { "title": "Fixture" },
+            "servers": [{ "url": "https://api.example.com/" }],
+            "paths": {
+                "/posts/{postIds}": {
+                    "get": {
+                        "operationId": "getPosts",
+                        "parameters": parameters,
+                        "responses": {
+                            "200": {
+                                "content": {
+                                    "application/json": {
+                                        "schema": { "$ref": "#/components/schemas/Post" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Post": {
+                        "required": ["id"],
+                        "properties": { "id": { "type": "integer" } }
+                    },
+                    "Filter": { "type": "string" }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn words_split_on_case_and_separators() {
+        assert_eq!(words("getPostById"), ["get", "post", "by", "id"]);
+        assert_eq!(words("user_id"), ["user", "id"]);
+        assert_eq!(words("X-Request-ID"), ["x", "request", "id"]);
+        assert_eq!(words("v2Posts"), ["v2", "posts"]);
+    }
+
+    #[test]
+    fn snake_case_escapes_keywords_and_digits() {
+        assert_eq!(snake_case("postId"), "post_id");
+        assert_eq!(snake_case("type"), "r#type");
+        assert_eq!(snake_case("2fa"), "_2fa");
+    }
+
+    #[test]
+    fn generate_encodes_array_params_per_item() {
+        let out = generate(&spec(json!([
+            {
+                "name": "postIds",
+                "in": "path",
+                "schema": { "type": "array", "items": { "type": "integer" } }
+            },
+            {
+                "name": "tag",
+                "in": "query",
+                "schema": { "type": "array", "items": { "type": "string" } }
+            }
+        ])))
+        .unwrap();
+
+        assert!(out.contains("pub const BASE_URL: &str = \"https://api.example.com\";"));
+        assert!(out.contains("#[derive(Clone, PartialEq, Debug, Default)]"));
+        assert!(out.contains("self.post_ids.iter().map(encode).collect::<Vec<_>>().join(\",\")"));
+        assert!(out.contains(
+            "        if let Some(value) = &self.tag {\n            for value in value {\n                query.push(format!(\"tag={}\", encode(value)));\n            }\n        }\n"
+        ));
+        assert!(out.contains("pub fn use_get_posts("));
+    }
+
+    #[test]
+    fn generate_skips_default_for_required_refs() {
+        let out = generate(&spec(json!([
+            { "name": "postIds", "in": "path", "schema": { "type": "integer" } },
+            {
+                "name": "filter",
+                "in": "query",
+                "required": true,
+                "schema": { "$ref": "#/components/schemas/Filter" }
+            }
+        ])))
+        .unwrap();
+
+        assert!(out.contains("#[derive(Clone, PartialEq, Debug)]\npub struct GetPostsParams"));
+        assert!(out.contains("pub filter: Filter,"));
+        assert!(out.contains("        let value = &self.filter;\n        query.push(format!(\"filter={}\", encode(value)));\n"));
+    }
+
+    #[test]
+    fn generate_requires_paths() {
+        assert!(generate(&json!({})).is_err());
+    }
+}
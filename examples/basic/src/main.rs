@@ -1,11 +1,16 @@
 // Port of https://github.com/tannerlinsley/react-query/blob/master/examples/basic
 
+// yew 0.19's `html!` emits component paths and props as bare statements,
+// and binds the props of components without any
+#![allow(clippy::unnecessary_operation, clippy::let_unit_value)]
+
 use reqwasm::http::Request;
 use serde::Deserialize;
+use std::time::Duration;
 use yew::{function_component, html, use_state, Callback, Html, Properties};
 use yew_query::{
-    query_response, use_query, QueryClient, QueryClientProvider, QueryOptions, QueryResult,
-    QueryState, Status,
+    use_is_fetching, use_query, use_query_with_params, QueryClient, QueryClientProvider,
+    QueryFilters, QueryKey, QueryOptions, QueryResult, QueryState, StaleTime, Status,
 };
 
 #[derive(Clone, PartialEq, Deserialize, Debug)]
@@ -15,31 +20,22 @@ pub struct Post {
     body: String,
 }
 
-query_response! {
-    Response {
-        post -> Post,
-        posts -> Vec<Post>
-    }
-}
-
-async fn get_posts() -> QueryResult<Response> {
-    Ok(Response::Posts(
-        Request::get("https://jsonplaceholder.typicode.com/posts")
-            .send()
-            .await
-            .map_err(|err| err.to_string())?
-            .json()
-            .await
-            .map_err(|err| err.to_string())?,
-    ))
+async fn get_posts() -> QueryResult<Vec<Post>> {
+    Request::get("https://jsonplaceholder.typicode.com/posts")
+        .send()
+        .await
+        .map_err(|err| err.to_string())?
+        .json()
+        .await
+        .map_err(|err| err.to_string())
 }
 
-fn use_posts() -> QueryState<Response> {
+fn use_posts() -> QueryState<Vec<Post>> {
     use_query(
         "posts",
         |_| Box::pin(get_posts()),
         QueryOptions {
-            stale_time: Some(3000),
+            stale_time: Some(StaleTime::After(Duration::from_secs(3))),
             ..Default::default()
         },
     )
@@ -60,7 +56,7 @@ fn posts(props: &PostsProps) -> Html {
             <h1>{ "Posts" }</h1>
             <div>
                 {
-                    match posts.status {
+                    match &posts.status {
                         Status::Idle => html! {},
                         Status::Loading => html! { "Loading..." },
                         Status::Success(data) => {
@@ -68,8 +64,6 @@ fn posts(props: &PostsProps) -> Html {
                                 <>
                                     <div>
                                         { data
-                                            .get_posts()
-                                            .to_owned()
                                             .iter()
                                             .map(|post| {
                                                 let post = post.clone();
@@ -88,7 +82,7 @@ fn posts(props: &PostsProps) -> Html {
                                         }
                                     </div>
                                     <div>{
-                                      if posts.is_fetching {
+                                      if posts.is_fetching() {
                                         html! { "Background Updating..." }
                                       } else {
                                         html! {}
@@ -107,22 +101,21 @@ fn posts(props: &PostsProps) -> Html {
     }
 }
 
-async fn get_post_by_id(id: usize) -> QueryResult<Response> {
-    Ok(Response::Post(
-        Request::get(format!("https://jsonplaceholder.typicode.com/posts/{}", id).as_ref())
-            .send()
-            .await
-            .map_err(|err| err.to_string())?
-            .json::<Post>()
-            .await
-            .map_err(|err| err.to_string())?,
-    ))
+async fn get_post_by_id(id: usize) -> QueryResult<Post> {
+    Request::get(format!("https://jsonplaceholder.typicode.com/posts/{}", id).as_ref())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?
+        .json()
+        .await
+        .map_err(|err| err.to_string())
 }
 
-fn use_post(post_id: usize) -> QueryState<Response> {
-    use_query(
-        format!("post/{}", post_id).as_ref(),
-        move |_| Box::pin(get_post_by_id(post_id)),
+fn use_post(post_id: usize) -> QueryState<Post> {
+    use_query_with_params(
+        "post",
+        post_id,
+        |post_id, _| Box::pin(get_post_by_id(post_id)),
         QueryOptions::default(),
     )
 }
@@ -147,11 +140,10 @@ fn post(props: &SinglePostProps) -> Html {
                 </a>
             </div>
             {
-                match post.status {
+                match &post.status {
                     Status::Idle => html! {},
                     Status::Loading => html! { "Loading..." },
-                    Status::Success(data) => {
-                        let post_data = data.get_post();
+                    Status::Success(post_data) => {
                         html! {
                             <>
                                 <h1>{ post_data.title.clone() }</h1>
@@ -159,7 +151,7 @@ fn post(props: &SinglePostProps) -> Html {
                                     <p>{ post_data.body.clone() }</p>
                                 </div>
                                 <div>{
-                                    if post.is_fetching {
+                                    if post.is_fetching() {
                                         html! { "Background Updating..." }
                                     } else {
                                         html! {}
@@ -177,6 +169,24 @@ fn post(props: &SinglePostProps) -> Html {
     }
 }
 
+// a global spinner for the header: counts every fetching query, and the
+// ones below `"post"` on their own
+#[function_component(FetchingIndicator)]
+fn fetching_indicator() -> Html {
+    let fetching = use_is_fetching(QueryFilters::all());
+    let fetching_posts = use_is_fetching(QueryKey::from("post"));
+
+    if fetching == 0 {
+        return html! {};
+    }
+
+    html! {
+        <div>
+            { format!("Refreshing {} queries ({} posts)...", fetching, fetching_posts) }
+        </div>
+    }
+}
+
 #[function_component(App)]
 fn app() -> Html {
     let client = use_state(QueryClient::new);
@@ -188,7 +198,8 @@ fn app() -> Html {
     };
 
     html! {
-        <QueryClientProvider<Response> client={(*client).clone()}>
+        <QueryClientProvider client={(*client).clone()}>
+            <FetchingIndicator />
             <p>
                 { "As you visit the posts below, you will notice them in a loading state the first time you load them. However, after you return to this list and click on any posts you have already visited again, you will see them load instantly and background refresh right before your eyes!" }
                 <strong>
@@ -202,8 +213,8 @@ fn app() -> Html {
                     html! { <SinglePost post_id={post_id.unwrap()} set_post_id={set_post_id} /> }
                 }
             }
-            // <QueryDevtools<Response> />
-        </QueryClientProvider<Response>>
+            // <QueryDevtools />
+        </QueryClientProvider>
     }
 }
 
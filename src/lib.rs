@@ -1,14 +1,84 @@
+// yew 0.19's `html!` emits component paths and props as bare statements
+#![allow(clippy::unnecessary_operation)]
+
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
 use std::cmp::PartialEq;
 use std::fmt::{self, Debug};
 use std::future::Future;
 use std::marker::PhantomData;
+use std::ops::Deref;
 use std::pin::Pin;
 use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+
+// goes through the client's `Logger`, the message is only formatted if
+// the logger is enabled. compiled out without the `logging` feature
+#[cfg(feature = "logging")]
+macro_rules! debug_log {
+    ($client:expr, $($arg:tt)*) => {{
+        let logger = &$client.logger;
+        if logger.enabled() {
+            logger.log(&format!($($arg)*));
+        }
+    }};
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! debug_log {
+    ($client:expr, $($arg:tt)*) => {{
+        let _ = &$client;
+    }};
+}
+
+// where the client's debug output goes, see `QueryClient::with_logger`
+pub trait Logger {
+    fn log(&self, message: &str);
+
+    // skips formatting messages nobody reads
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+// the default unless the `logging` feature is enabled
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NoopLogger;
+
+impl Logger for NoopLogger {
+    fn log(&self, _message: &str) {}
+
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+// writes to the browser console, the default with the `logging` feature
+#[cfg(feature = "logging")]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ConsoleLogger;
+
+#[cfg(feature = "logging")]
+impl Logger for ConsoleLogger {
+    fn log(&self, message: &str) {
+        web_sys::console::log_1(&message.into());
+    }
+}
+
+fn default_logger() -> Rc<dyn Logger> {
+    #[cfg(feature = "logging")]
+    return Rc::new(ConsoleLogger);
+    #[cfg(not(feature = "logging"))]
+    Rc::new(NoopLogger)
+}
 
 type CB<Arg, Rt> = dyn Fn(Arg) -> Pin<Box<dyn Future<Output = Rt>>>;
 
 pub struct FnPtr<Arg, Rt> {
     cb: Rc<CB<Arg, Rt>>,
+    type_id: TypeId,
+    id: Option<Rc<str>>,
 }
 
 impl<Arg, Rt, F> From<F> for FnPtr<Arg, Rt>
@@ -16,13 +86,17 @@ where
     F: 'static + Fn(Arg) -> Pin<Box<dyn Future<Output = Rt>>>,
 {
     fn from(func: F) -> Self {
-        FnPtr { cb: Rc::new(func) }
+        FnPtr {
+            cb: Rc::new(func),
+            type_id: TypeId::of::<F>(),
+            id: None,
+        }
     }
 }
 
 impl<Arg, Rt> PartialEq for FnPtr<Arg, Rt> {
     fn eq(&self, other: &Self) -> bool {
-        #[allow(clippy::vtable_address_comparisons)]
+        #[allow(ambiguous_wide_pointer_comparisons)]
         Rc::ptr_eq(&self.cb, &other.cb)
     }
 }
@@ -31,6 +105,8 @@ impl<Arg, Rt> Clone for FnPtr<Arg, Rt> {
     fn clone(&self) -> Self {
         Self {
             cb: self.cb.clone(),
+            type_id: self.type_id,
+            id: self.id.clone(),
         }
     }
 }
@@ -42,6 +118,29 @@ impl<Arg, Rt> fmt::Debug for FnPtr<Arg, Rt> {
 }
 
 impl<Arg, Rt> FnPtr<Arg, Rt> {
+    // names the function for `is_same_fn`
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into().into());
+        self
+    }
+
+    // every render creates a new `FnPtr`, so pointer equality alone can't
+    // tell whether two pointers wrap the same function. if both have an id
+    // the ids decide, otherwise the closure types do: closures from the
+    // same call site share a type, closures from different call sites
+    // don't. functions coerced to the same `fn` pointer or boxed into the
+    // same type count as the same without an id
+    pub fn is_same_fn(&self, other: &Self) -> bool {
+        #[allow(ambiguous_wide_pointer_comparisons)]
+        if Rc::ptr_eq(&self.cb, &other.cb) {
+            return true;
+        }
+        match (&self.id, &other.id) {
+            (Some(id), Some(other)) => id == other,
+            _ => self.type_id == other.type_id,
+        }
+    }
+
     pub async fn emit(&self, value: Arg) -> Rt {
         let x = (self.cb)(value);
         x.await
@@ -52,635 +151,7241 @@ pub fn now() -> i64 {
     instant::now() as i64
 }
 
+async fn sleep(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .expect("Couldn't access `window`")
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+            .expect("`setTimeout` didn't register");
+    });
+
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+// runs `futures` concurrently, resolving once every one of them has
+async fn join_all(futures: Vec<Pin<Box<dyn Future<Output = ()>>>>) {
+    let promises = futures
+        .into_iter()
+        .map(|future| {
+            let mut resolve = None;
+            let promise = js_sys::Promise::new(&mut |res, _| resolve = Some(res));
+            let resolve = resolve.expect("`Promise` executor runs synchronously");
+
+            wasm_bindgen_futures::spawn_local(async move {
+                future.await;
+                let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+            });
+            promise
+        })
+        .collect::<js_sys::Array>();
+
+    let _ = wasm_bindgen_futures::JsFuture::from(js_sys::Promise::all(&promises)).await;
+}
+
+fn is_online() -> bool {
+    web_sys::window()
+        .expect("Couldn't access `window`")
+        .navigator()
+        .on_line()
+}
+
+// whether the page is in the foreground, `document.hidden` is set while
+// the tab is in the background or the window minimized
+fn is_visible() -> bool {
+    web_sys::window()
+        .expect("Couldn't access `window`")
+        .document()
+        .is_none_or(|document| !document.hidden())
+}
+
+async fn until_online() {
+    if is_online() {
+        return;
+    }
+
+    let mut resolve = None;
+    let promise = js_sys::Promise::new(&mut |res, _| resolve = Some(res));
+    let resolve = resolve.expect("`Promise` executor runs synchronously");
+    let window = web_sys::window().expect("Couldn't access `window`");
+
+    window
+        .add_event_listener_with_callback("online", &resolve)
+        .unwrap();
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+    window
+        .remove_event_listener_with_callback("online", &resolve)
+        .unwrap();
+}
+
+// how fetching behaves while the browser is offline
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum NetworkMode {
+    // pause until the browser is back online
+    #[default]
+    Online,
+    // run regardless of connectivity
+    Always,
+    // make the first attempt regardless, pause retries while offline
+    OfflineFirst,
+}
+
+// the order fetches waiting for `QueryClient::max_concurrent_fetches` get
+// a slot in, first come first served within a priority
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum FetchPriority {
+    // the default of `QueryClient::prefetch_query`
+    Low,
+    #[default]
+    Normal,
+    // e.g. the content above the fold
+    High,
+}
+
+// how long fetched data counts as fresh. with `Infinite` it only goes
+// stale when invalidated
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StaleTime {
+    After(Duration),
+    Infinite,
+}
+
+impl StaleTime {
+    pub(crate) fn as_millis(self) -> i64 {
+        match self {
+            StaleTime::After(stale_time) => {
+                i64::try_from(stale_time.as_millis()).unwrap_or(i64::MAX)
+            }
+            StaleTime::Infinite => i64::MAX,
+        }
+    }
+
+    pub(crate) fn from_millis(stale_time: i64) -> Self {
+        match u64::try_from(stale_time) {
+            Ok(stale_time) if stale_time < i64::MAX as u64 => {
+                StaleTime::After(Duration::from_millis(stale_time))
+            }
+            Ok(_) => StaleTime::Infinite,
+            Err(_) => StaleTime::After(Duration::ZERO),
+        }
+    }
+}
+
+impl From<Duration> for StaleTime {
+    fn from(stale_time: Duration) -> Self {
+        StaleTime::After(stale_time)
+    }
+}
+
+// how long an unused query (or a settled mutation) stays cached. with
+// `Infinite` it's never garbage collected
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GcTime {
+    After(Duration),
+    Infinite,
+}
+
+impl GcTime {
+    // `None` for never
+    pub(crate) fn as_millis(self) -> Option<i32> {
+        match self {
+            GcTime::After(gc_time) => Some(millis(gc_time)),
+            GcTime::Infinite => None,
+        }
+    }
+
+    pub(crate) fn from_millis(gc_time: Option<i32>) -> Self {
+        match gc_time {
+            Some(gc_time) => GcTime::After(Duration::from_millis(gc_time.max(0) as u64)),
+            None => GcTime::Infinite,
+        }
+    }
+}
+
+impl From<Duration> for GcTime {
+    fn from(gc_time: Duration) -> Self {
+        GcTime::After(gc_time)
+    }
+}
+
+// what timers are set with, long durations saturate at ~24 days
+fn millis(duration: Duration) -> i32 {
+    i32::try_from(duration.as_millis()).unwrap_or(i32::MAX)
+}
+
+// whether a newly mounted observer revalidates data that's already cached
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum RefetchOnMount {
+    // even if it's fresh
+    Always,
+    // once it's older than `stale_time`
+    #[default]
+    IfStale,
+    // rely on the cache, only fetch if there's no data yet
+    Never,
+}
+
+const MAX_RETRY_DELAY_MS: i32 = 30 * 1000;
+
+// `base` ms doubled for every failure so far, capped at 30s, plus up to a
+// quarter of that at random so clients failing together don't all retry
+// at the same moment
+fn backoff(base: i32, failures: u32) -> i32 {
+    backoff_with(base, failures, js_sys::Math::random())
+}
+
+// `backoff` with the random number in [0, 1) passed in
+fn backoff_with(base: i32, failures: u32, random: f64) -> i32 {
+    let delay = (0..failures)
+        .fold(base, |delay, _| delay.saturating_mul(2))
+        .min(MAX_RETRY_DELAY_MS);
+    let jitter = random * f64::from(delay) / 4.0;
+
+    delay.saturating_add(jitter as i32)
+}
+
 pub type QueryResult<TData> = Result<TData, String>;
 
 mod utils {
-    use super::{now, FnPtr, QueryResult};
-    use std::cell::RefCell;
+    use super::{
+        backoff, default_logger, is_online, join_all, millis, now,
+        plugins::{FocusRefetch, ReconnectRefetch},
+        sleep, until_online, DefaultQueryOptions, FetchPriority, FnPtr, GcTime, Logger, Mutation,
+        MutationCache, MutationDefaults, NetworkMode, QueryFilters, QueryKey, QueryResult,
+        RefetchOnMount, StaleTime,
+    };
+    use std::any::{Any, TypeId};
+    use std::cell::{Cell, RefCell};
     use std::cmp::PartialEq;
+    use std::collections::{BTreeMap, HashMap, VecDeque};
     use std::fmt::Debug;
-    use std::rc::Rc;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::{Rc, Weak};
+    use std::task::Poll;
+    use std::time::Duration;
     use wasm_bindgen::JsCast;
     use yew::Callback;
 
     #[derive(Clone)]
     pub struct QueryOptions<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: Clone + 'static,
     {
         pub query_key: String,
-        pub query_fn: FnPtr<(), QueryResult<TData>>,
+        pub query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
         pub stale_time: i64,
-        pub cache_time: i32,
+        // `None` for never
+        pub cache_time: Option<i32>,
+        pub live: Option<LiveQuery<TData>>,
+        pub is_equal: Option<DataEq<TData>>,
+        // falls back to the client's triggers
+        pub refetch_triggers: Option<RefetchTriggers>,
+        pub refetch_on_reconnect: Option<bool>,
+        pub refetch_on_mount: Option<RefetchOnMount>,
+        pub network_mode: NetworkMode,
+        pub priority: FetchPriority,
+        pub retry: Retry,
+        pub retry_delay: RetryDelay,
+        pub fetch_timeout: Option<i32>,
+        // with the time it was last updated, `None` meaning now
+        pub initial_data: Option<(InitialData<TData>, Option<i64>)>,
+        pub meta: Option<QueryMeta>,
+        pub enabled: bool,
     }
 
-    type Queries<TData> = Rc<RefCell<Vec<Rc<RefCell<Query<TData>>>>>>;
-
-    #[derive(PartialEq, Debug)]
-    pub struct QueryClient<TData>
-    where
-        TData: Clone + PartialEq + Debug + 'static,
-    {
-        pub queries: Queries<TData>,
-        subscribers: Rc<RefCell<Vec<Callback<()>>>>,
+    // what the query function is called with on every fetch
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct QueryFunctionContext {
+        pub query_key: String,
+        // fires when the fetch is cancelled, see `QueryClient::cancel_queries`
+        pub signal: web_sys::AbortSignal,
+        pub meta: Option<QueryMeta>,
     }
 
-    impl<TData> Clone for QueryClient<TData>
-    where
-        TData: Clone + PartialEq + Debug,
-    {
-        fn clone(&self) -> Self {
-            Self {
-                queries: Rc::clone(&self.queries),
-                subscribers: Rc::clone(&self.subscribers),
-            }
+    impl QueryFunctionContext {
+        // the parts of a structured key, `None` for plain string keys
+        pub fn key(&self) -> Option<QueryKey> {
+            QueryKey::parse(&self.query_key)
         }
     }
 
-    impl<TData> QueryClient<TData>
-    where
-        TData: Clone + PartialEq + Debug,
-    {
-        pub fn new() -> Self {
-            Self {
-                queries: Rc::new(RefCell::new(vec![])),
-                subscribers: Rc::new(RefCell::new(vec![])),
-            }
-        }
-
-        pub async fn invalidate_queries(&self, query_key: &str) {
-            let queries = (*self.queries).borrow_mut();
-            let query = queries
-                .iter()
-                .find(|&query| query.borrow().query_key == query_key);
+    // arbitrary data handed to the query function through its context,
+    // e.g. headers shared by every query of a screen
+    #[derive(Clone)]
+    pub struct QueryMeta(Rc<dyn Any>);
 
-            if let Some(query) = query {
-                (*query).borrow_mut().fetch().await
-            }
+    impl QueryMeta {
+        pub fn new(meta: impl Any) -> Self {
+            Self(Rc::new(meta))
         }
 
-        fn get_query(&mut self, options: &QueryOptions<TData>) -> Rc<RefCell<Query<TData>>> {
-            let query_key = options.query_key.clone();
-            let mut queries = (*self.queries).borrow_mut();
-            let query = queries
-                .iter()
-                .find(|&query| query.borrow().query_key == query_key);
-
-            // web_sys::console::log_1(&format!("{:#?}", self).into());
-
-            if let Some(query) = query {
-                web_sys::console::log_1(&format!("query found {:#?}", *query).into());
-                Rc::clone(query)
-            } else {
-                let mut query = create_query(self.clone(), options);
-                query.state.status = Status::Loading;
-                let query = Rc::new(RefCell::new(query));
-                queries.push(Rc::clone(&query));
-                // web_sys::console::log_1(&format!("Updated: {:#?}", self).into());
-
-                query
-            }
+        pub fn get<T: Any>(&self) -> Option<&T> {
+            self.0.downcast_ref()
         }
+    }
 
-        pub fn notify(&self) {
-            for subscriber in (*self.subscribers).borrow().iter() {
-                subscriber.emit(())
-            }
+    impl PartialEq for QueryMeta {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.0, &other.0)
         }
+    }
 
-        pub fn subscribe(&mut self, callback: Callback<()>) {
-            (*self.subscribers).borrow_mut().push(callback);
+    impl Debug for QueryMeta {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("QueryMeta")
         }
+    }
 
-        pub fn unsubscribe(&mut self, callback: Callback<()>) {
-            (*self.subscribers)
-                .borrow_mut()
-                .retain(|subscriber| subscriber.clone() == callback)
-        }
+    // seeds a query that isn't cached yet
+    #[derive(Clone)]
+    pub enum InitialData<TData> {
+        Value(TData),
+        // only called if the query isn't cached yet
+        Fn(Rc<dyn Fn() -> TData>),
     }
 
-    impl<TData> Default for QueryClient<TData>
+    impl<TData> InitialData<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: Clone,
     {
-        fn default() -> Self {
-            Self::new()
+        fn get(&self) -> TData {
+            match self {
+                InitialData::Value(data) => data.clone(),
+                InitialData::Fn(initial_data) => initial_data(),
+            }
         }
     }
 
-    #[derive(Clone, PartialEq, Debug)]
-    pub enum Status<TData>
+    impl<TData> PartialEq for InitialData<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: PartialEq,
     {
-        Idle,
-        Loading,
-        Success(TData),
-        Error(String),
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (InitialData::Value(a), InitialData::Value(b)) => a == b,
+                (InitialData::Fn(a), InitialData::Fn(b)) => Rc::ptr_eq(a, b),
+                _ => false,
+            }
+        }
     }
 
-    #[derive(Clone, PartialEq, Debug)]
-    pub struct Query<TData>
+    impl<TData> Debug for InitialData<TData>
     where
-        TData: Clone + PartialEq + Debug + 'static,
+        TData: Debug,
     {
-        // change to lifetime reference
-        client: QueryClient<TData>,
-        pub state: QueryState<TData>,
-        pub query_fn: FnPtr<(), QueryResult<TData>>,
-        pub subscribers: Vec<(Subscriber<TData>, Callback<()>)>,
-        pub query_key: String,
-        pub cache_time: i32,
-        timeout: Option<i32>,
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                InitialData::Value(data) => f.debug_tuple("Value").field(data).finish(),
+                InitialData::Fn(_) => f.write_str("Fn"),
+            }
+        }
     }
 
-    impl<TData> Query<TData>
-    where
-        TData: Clone + PartialEq + Debug,
-    {
-        pub async fn fetch(&mut self) {
-            web_sys::console::log_1(&"updating state".into());
-            web_sys::console::log_1(&format!("{:#?}", self.state).into());
+    pub type IsEqual<TData> = Rc<dyn Fn(&TData, &TData) -> bool>;
 
-            self.set_state(|old| QueryState {
-                is_fetching: true,
-                ..old
-            });
+    // called with the number of failures so far and the last error
+    pub type RetryIf<TError = str> = Rc<dyn Fn(u32, &TError) -> bool>;
 
-            match self.query_fn.emit(()).await {
-                Ok(data) => {
-                    self.set_state(|old| QueryState {
-                        status: Status::Success(data.clone()),
-                        last_updated: Some(now()),
-                        ..old
-                    });
-                }
-                Err(err) => self.set_state(|old| QueryState {
-                    status: Status::Error(err.clone()),
-                    ..old
-                }),
-            };
+    // whether a failed fetch is tried again. mutations use it with their
+    // own error type
+    pub enum Retry<TError: ?Sized = str> {
+        // up to this many more attempts
+        Count(u32),
+        If(RetryIf<TError>),
+    }
 
-            self.set_state(|old| QueryState {
-                is_fetching: false,
-                ..old
-            });
+    impl<TError: ?Sized> Retry<TError> {
+        pub(crate) fn should_retry(&self, failures: u32, error: &TError) -> bool {
+            match self {
+                Retry::Count(count) => failures < *count,
+                Retry::If(predicate) => predicate(failures, error),
+            }
+        }
+    }
+
+    impl<TError: ?Sized> Clone for Retry<TError> {
+        fn clone(&self) -> Self {
+            match self {
+                Retry::Count(count) => Retry::Count(*count),
+                Retry::If(predicate) => Retry::If(predicate.clone()),
+            }
+        }
+    }
 
-            web_sys::console::log_1(&"new state".into());
-            web_sys::console::log_1(&format!("{:#?}", self.state).into());
+    impl<TError: ?Sized> Default for Retry<TError> {
+        fn default() -> Self {
+            Retry::Count(0)
         }
+    }
 
-        fn set_state(&mut self, updater: impl Fn(QueryState<TData>) -> QueryState<TData>) {
-            self.state = updater(self.state.clone());
-            for (_, cb) in &self.subscribers {
-                cb.emit(());
+    impl<TError: ?Sized> PartialEq for Retry<TError> {
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (Retry::Count(a), Retry::Count(b)) => a == b,
+                (Retry::If(a), Retry::If(b)) => Rc::ptr_eq(a, b),
+                _ => false,
             }
-            self.client.notify();
         }
+    }
 
-        fn subscribe(&mut self, subscriber: Subscriber<TData>, callback: Callback<()>) {
-            self.subscribers.push((subscriber, callback));
-            self.unschedule_query_cleanup();
+    impl<TError: ?Sized> Debug for Retry<TError> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Retry::Count(count) => f.debug_tuple("Count").field(count).finish(),
+                Retry::If(_) => f.write_str("If"),
+            }
         }
+    }
 
-        fn unsubscribe(&mut self, callback: Callback<()>) {
-            self.subscribers = self
-                .subscribers
-                .iter()
-                .cloned()
-                // if stored callback and callback passed to `unsubscribe`
-                // are equal, then the subscribers should also be equal
-                // since they are created at the same time
-                .filter(|(_, cb)| cb.clone() == callback)
-                .collect::<Vec<_>>();
+    // called with the number of failures so far and the last error
+    pub type RetryDelayFn<TError = str> = Rc<dyn Fn(u32, &TError) -> Duration>;
 
-            if self.subscribers.is_empty() {
-                self.schedule_query_cleanup();
+    // how long to wait before retrying a failed fetch (or mutation)
+    pub enum RetryDelay<TError: ?Sized = str> {
+        // starting at this delay and doubling after every failure, up to 30s,
+        // with some jitter
+        Exponential(Duration),
+        Fn(RetryDelayFn<TError>),
+    }
+
+    impl<TError: ?Sized> RetryDelay<TError> {
+        // in ms
+        pub(crate) fn delay(&self, failures: u32, error: &TError) -> i32 {
+            match self {
+                RetryDelay::Exponential(base) => backoff(millis(*base), failures),
+                RetryDelay::Fn(delay) => millis(delay(failures, error)),
             }
         }
+    }
 
-        fn schedule_query_cleanup(&mut self) {
-            let query_key = self.query_key.clone();
-            let queries = (self.client.queries).clone();
-            let client = self.client.clone();
+    impl<TError: ?Sized> Clone for RetryDelay<TError> {
+        fn clone(&self) -> Self {
+            match self {
+                RetryDelay::Exponential(base) => RetryDelay::Exponential(*base),
+                RetryDelay::Fn(delay) => RetryDelay::Fn(delay.clone()),
+            }
+        }
+    }
 
-            let timeout = web_sys::window()
-                .expect("Couldn't access `window`")
-                .set_timeout_with_callback_and_timeout_and_arguments_0(
-                    wasm_bindgen::closure::Closure::wrap(Box::new(move || {
-                        let query_key = query_key.clone();
-                        queries
-                            .borrow_mut()
-                            .retain(move |query| (*query).borrow_mut().query_key != query_key);
+    impl<TError: ?Sized> Default for RetryDelay<TError> {
+        fn default() -> Self {
+            RetryDelay::Exponential(Duration::from_secs(1))
+        }
+    }
 
-                        client.notify()
-                    })
-                        as Box<dyn FnMut()>)
-                    .as_ref()
-                    .unchecked_ref(),
-                    self.cache_time,
-                )
-                .expect("`setTimeout` didn't register");
+    impl<TError: ?Sized> From<Duration> for RetryDelay<TError> {
+        fn from(base: Duration) -> Self {
+            RetryDelay::Exponential(base)
+        }
+    }
 
-            self.timeout = Some(timeout);
+    impl<TError: ?Sized> PartialEq for RetryDelay<TError> {
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (RetryDelay::Exponential(a), RetryDelay::Exponential(b)) => a == b,
+                (RetryDelay::Fn(a), RetryDelay::Fn(b)) => Rc::ptr_eq(a, b),
+                _ => false,
+            }
         }
+    }
 
-        fn unschedule_query_cleanup(&mut self) {
-            if let Some(timeout) = self.timeout {
-                web_sys::window()
-                    .expect("Couldn't access `window`")
-                    .clear_timeout_with_handle(timeout)
+    impl<TError: ?Sized> Debug for RetryDelay<TError> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                RetryDelay::Exponential(base) => f.debug_tuple("Exponential").field(base).finish(),
+                RetryDelay::Fn(_) => f.write_str("Fn"),
             }
         }
     }
 
-    #[derive(Clone, PartialEq, Debug)]
-    pub struct QueryState<TData>
+    // called with the observed state before every poll. `None` stops
+    // polling until the component remounts or its query key changes
+    pub type RefetchIntervalFn<TData> = Rc<dyn Fn(&QueryState<TData>) -> Option<Duration>>;
+
+    // how often a mounted query is polled
+    pub enum RefetchInterval<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: Clone,
     {
-        pub status: Status<TData>,
-        pub is_fetching: bool,
-        pub last_updated: Option<i64>,
+        Every(Duration),
+        // decided by the latest data, e.g. stop once a job reports it's done
+        Fn(RefetchIntervalFn<TData>),
     }
 
-    impl<TData> QueryState<TData>
+    impl<TData> RefetchInterval<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: Clone,
     {
-        pub fn refetch() {
-            todo!()
+        // in ms
+        pub(crate) fn next(&self, state: &QueryState<TData>) -> Option<i32> {
+            match self {
+                RefetchInterval::Every(interval) => Some(millis(*interval)),
+                RefetchInterval::Fn(interval) => interval(state).map(millis),
+            }
         }
     }
 
-    fn create_query<TData>(
-        client: QueryClient<TData>,
-        options: &QueryOptions<TData>,
-    ) -> Query<TData>
+    impl<TData> Clone for RefetchInterval<TData>
     where
-        TData: Clone + PartialEq + Debug,
+        TData: Clone,
     {
-        Query {
-            client,
-            state: QueryState {
-                status: Status::Idle,
-                is_fetching: true,
-                last_updated: None,
-            },
-            query_fn: options.query_fn.clone(),
-            subscribers: vec![],
-            query_key: options.query_key.clone(),
-            cache_time: options.cache_time,
-            timeout: None,
+        fn clone(&self) -> Self {
+            match self {
+                RefetchInterval::Every(interval) => RefetchInterval::Every(*interval),
+                RefetchInterval::Fn(interval) => RefetchInterval::Fn(Rc::clone(interval)),
+            }
         }
     }
 
-    #[derive(Clone, PartialEq, Debug)]
-    pub struct Subscriber<TData>
+    impl<TData> From<Duration> for RefetchInterval<TData>
     where
-        TData: Clone + PartialEq + Debug + 'static,
+        TData: Clone,
     {
-        query: Rc<RefCell<Query<TData>>>,
-        stale_time: i64,
-        cache_time: i32,
+        fn from(interval: Duration) -> Self {
+            RefetchInterval::Every(interval)
+        }
     }
 
-    impl<T> Drop for Subscriber<T>
+    impl<TData> Debug for RefetchInterval<TData>
     where
-        T: Clone + PartialEq + Debug + 'static,
+        TData: Clone,
     {
-        fn drop(&mut self) {
-            web_sys::console::log_1(&"DROPPING SUBSCRIBER".into())
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                RefetchInterval::Every(interval) => f.debug_tuple("Every").field(interval).finish(),
+                RefetchInterval::Fn(_) => f.write_str("Fn"),
+            }
         }
     }
 
-    impl<TData> Subscriber<TData>
-    where
-        TData: Clone + PartialEq + Debug + 'static,
-    {
-        pub fn get_result(&self) -> QueryState<TData> {
-            let y = Rc::clone(&self.query);
-            web_sys::console::log_1(&"`get_result`: TRYING TO BORROW".into());
-            let x = (*y).borrow();
-            let state = x.state.clone();
-            std::mem::drop(x);
-            state
+    // decides whether refetched or pushed data counts as a change, without
+    // it every new value does
+    #[derive(Clone)]
+    pub struct DataEq<TData>(pub IsEqual<TData>);
+
+    impl<TData> PartialEq for DataEq<TData> {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.0, &other.0)
+        }
+    }
+
+    impl<TData> Debug for DataEq<TData> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("DataEq")
+        }
+    }
+
+    type Queries = BTreeMap<String, Rc<dyn AnyQuery>>;
+
+    // every query of a client, by query key, ordered so listings keep
+    // related keys together. see `QueryClient::query_cache`
+    #[derive(Clone, Default)]
+    pub struct QueryCache {
+        queries: Rc<RefCell<Queries>>,
+    }
+
+    impl PartialEq for QueryCache {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.queries, &other.queries)
+        }
+    }
+
+    impl Debug for QueryCache {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("QueryCache")
+                .field(
+                    "queries",
+                    &self.queries.try_borrow().map(|queries| queries.len()),
+                )
+                .finish()
+        }
+    }
+
+    impl QueryCache {
+        pub fn find(&self, query_key: &str) -> Option<QueryHandle> {
+            let query = self.queries.borrow().get(query_key).cloned()?;
+            Some(QueryHandle { query })
+        }
+
+        pub fn find_all(&self, filters: impl Into<QueryFilters>) -> Vec<QueryHandle> {
+            let filters = filters.into();
+            self.queries
+                .borrow()
+                .values()
+                .filter(|&query| filters.matches(&**query))
+                .map(|query| QueryHandle {
+                    query: Rc::clone(query),
+                })
+                .collect()
+        }
+
+        pub fn len(&self) -> usize {
+            self.queries.borrow().len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.queries.borrow().is_empty()
+        }
+
+        pub(crate) fn borrow(&self) -> std::cell::Ref<'_, Queries> {
+            self.queries.borrow()
+        }
+
+        pub(crate) fn borrow_mut(&self) -> std::cell::RefMut<'_, Queries> {
+            self.queries.borrow_mut()
+        }
+
+        pub(crate) fn try_borrow(
+            &self,
+        ) -> Result<std::cell::Ref<'_, Queries>, std::cell::BorrowError> {
+            self.queries.try_borrow()
+        }
+    }
+
+    // a cached query as seen from `QueryCache::find`. it reads the query
+    // at the time of the call, and keeps it alive while held
+    #[derive(Clone)]
+    pub struct QueryHandle {
+        query: Rc<dyn AnyQuery>,
+    }
+
+    impl PartialEq for QueryHandle {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.query, &other.query)
+        }
+    }
+
+    impl Debug for QueryHandle {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("QueryHandle")
+                .field("query_key", &self.query.query_key())
+                .field("status", &self.query.status())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl QueryHandle {
+        pub fn query_key(&self) -> String {
+            self.query.query_key()
+        }
+
+        pub fn data_type_name(&self) -> &'static str {
+            self.query.data_type_name()
+        }
+
+        pub fn status(&self) -> QueryStatusState {
+            self.query.status()
+        }
+
+        pub fn error(&self) -> Option<String> {
+            self.query.error()
+        }
+
+        pub fn last_updated(&self) -> Option<i64> {
+            self.query.last_updated()
+        }
+
+        pub fn is_stale(&self) -> bool {
+            self.query.is_stale()
+        }
+
+        pub fn observer_count(&self) -> usize {
+            self.query.subscriber_count()
+        }
+
+        pub fn options(&self) -> QueryHandleOptions {
+            self.query.options()
+        }
+
+        // `None` if the query holds something other than `TData`
+        pub fn state<TData>(&self) -> Option<QueryState<TData>>
+        where
+            TData: Clone + 'static,
+        {
+            let query = Rc::clone(&self.query)
+                .into_any()
+                .downcast::<RefCell<Query<TData>>>()
+                .ok()?;
+            let state = query.borrow().state.clone();
+            Some(state)
+        }
+    }
+
+    // what a cached query currently runs with, combined across its
+    // subscribers
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct QueryHandleOptions {
+        // the shortest among its subscribers, `None` if it has none
+        pub stale_time: Option<StaleTime>,
+        pub cache_time: GcTime,
+        pub refetch_triggers: RefetchTriggers,
+        pub network_mode: NetworkMode,
+        pub priority: FetchPriority,
+    }
+
+    // a cached query with its data type erased, so a single client can hold
+    // queries of every data type. implemented by `RefCell<Query<TData>>`,
+    // `into_any` gets the typed query back
+    pub trait AnyQuery {
+        fn query_key(&self) -> String;
+        fn data_type(&self) -> TypeId;
+        // `std::any::type_name` of the data type, for diagnostics
+        fn data_type_name(&self) -> &'static str;
+        fn status(&self) -> QueryStatusState;
+        fn error(&self) -> Option<String>;
+        fn last_updated(&self) -> Option<i64>;
+        fn is_stale(&self) -> bool;
+        fn subscriber_count(&self) -> usize;
+        // when it was last requested or (un)subscribed to
+        fn last_used(&self) -> i64;
+        // the `Rc<TData>` of a successful query
+        fn data(&self) -> Option<Rc<dyn Any>>;
+        fn refetch_triggers(&self) -> RefetchTriggers;
+        fn options(&self) -> QueryHandleOptions;
+        // marks the query stale, returning the refetch if it's mounted
+        fn invalidate(&self) -> Option<Pin<Box<dyn Future<Output = ()>>>>;
+        // fetches regardless of staleness
+        fn refetch(&self) -> Pin<Box<dyn Future<Output = ()>>>;
+        // refetches for every stale subscriber that opted into `trigger`
+        fn fetch_subscribers(&self, trigger: fn(&RefetchTriggers) -> bool);
+        // aborts the running fetch, if any
+        fn cancel(&self);
+        // called once the query was taken out of the cache, its mounted
+        // subscribers start over with a new query
+        fn detach(&self);
+        // back to the state it was created with, returning the refetch if
+        // it's mounted
+        fn reset(&self) -> Option<Pin<Box<dyn Future<Output = ()>>>>;
+        fn into_any(self: Rc<Self>) -> Rc<dyn Any>;
+    }
+
+    impl<TData> AnyQuery for RefCell<Query<TData>>
+    where
+        TData: Clone + 'static,
+    {
+        fn query_key(&self) -> String {
+            self.borrow().query_key.clone()
+        }
+
+        fn data_type(&self) -> TypeId {
+            TypeId::of::<TData>()
+        }
+
+        fn data_type_name(&self) -> &'static str {
+            std::any::type_name::<TData>()
+        }
+
+        fn status(&self) -> QueryStatusState {
+            let query = self.borrow();
+            QueryStatusState {
+                status: query.state.status.kind(),
+                fetch_status: query.state.fetch_status,
+            }
+        }
+
+        fn error(&self) -> Option<String> {
+            match &self.borrow().state.status {
+                Status::Error(err) => Some(err.clone()),
+                _ => None,
+            }
+        }
+
+        fn last_updated(&self) -> Option<i64> {
+            self.borrow().state.last_updated
+        }
+
+        fn is_stale(&self) -> bool {
+            self.borrow().is_stale()
+        }
+
+        fn subscriber_count(&self) -> usize {
+            self.borrow()
+                .subscribers
+                .iter()
+                .filter(|subscriber| subscriber.is_alive())
+                .count()
+        }
+
+        fn last_used(&self) -> i64 {
+            self.borrow().last_used
+        }
+
+        fn data(&self) -> Option<Rc<dyn Any>> {
+            match &self.borrow().state.status {
+                Status::Success(data) => Some(Rc::clone(data) as Rc<dyn Any>),
+                _ => None,
+            }
+        }
+
+        fn refetch_triggers(&self) -> RefetchTriggers {
+            self.borrow().refetch_triggers()
+        }
+
+        fn options(&self) -> QueryHandleOptions {
+            let query = self.borrow();
+            QueryHandleOptions {
+                stale_time: query
+                    .subscribers
+                    .iter()
+                    .filter(|subscriber| subscriber.is_alive())
+                    .map(|subscriber| subscriber.stale_time)
+                    .min()
+                    .map(StaleTime::from_millis),
+                cache_time: GcTime::from_millis(query.cache_time),
+                refetch_triggers: query.refetch_triggers(),
+                network_mode: query.network_mode,
+                priority: query.priority,
+            }
+        }
+
+        fn invalidate(&self) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+            let mut query = self.borrow_mut();
+            query.set_state(|old| QueryState {
+                is_invalidated: true,
+                ..old
+            });
+            query.client.emit_event(QueryEvent::Invalidated {
+                query_key: query.query_key.clone(),
+            });
+            if !query.has_enabled_subscribers() {
+                return None;
+            }
+
+            Some(query.shared_fetch())
+        }
+
+        fn refetch(&self) -> Pin<Box<dyn Future<Output = ()>>> {
+            self.borrow().shared_fetch()
+        }
+
+        fn fetch_subscribers(&self, trigger: fn(&RefetchTriggers) -> bool) {
+            let query = self.borrow();
+            let stale_time = query
+                .subscribers
+                .iter()
+                .filter(|subscriber| subscriber.is_alive() && trigger(&subscriber.refetch_triggers))
+                .map(|subscriber| subscriber.stale_time)
+                .min();
+
+            if let Some(stale_time) = stale_time {
+                query.fetch_if_older_than(stale_time)
+            }
+        }
+
+        fn cancel(&self) {
+            let abort = self.borrow().abort.borrow_mut().take();
+            if let Some(controller) = abort {
+                controller.abort()
+            }
+        }
+
+        fn detach(&self) {
+            self.cancel();
+            let mut query = self.borrow_mut();
+            query.removed = true;
+            query.unschedule_query_cleanup();
+            query.set_state(|old| QueryState {
+                status: Status::Loading,
+                fetch_status: FetchStatus::Idle,
+                last_updated: None,
+                ..old
+            });
+        }
+
+        fn reset(&self) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+            let mut query = self.borrow_mut();
+            let initial_data = query.initial_data.clone();
+            query.set_state(|_| {
+                let initial = QueryState {
+                    status: Status::Loading,
+                    fetch_status: FetchStatus::Idle,
+                    is_previous_data: false,
+                    last_updated: None,
+                    is_invalidated: false,
+                    is_placeholder_data: false,
+                    is_stale: true,
+                };
+                match &initial_data {
+                    Some((initial_data, updated_at)) => QueryState {
+                        status: Status::Success(Rc::new(initial_data.get())),
+                        last_updated: Some(updated_at.unwrap_or_else(now)),
+                        ..initial
+                    },
+                    None => initial,
+                }
+            });
+            if !query.has_enabled_subscribers() {
+                return None;
+            }
+
+            Some(query.shared_fetch())
+        }
+
+        fn into_any(self: Rc<Self>) -> Rc<dyn Any> {
+            self
+        }
+    }
+
+    // handed to a live query's `subscribe` function, pushes values coming
+    // from a realtime source into the cached data
+    pub struct LiveSink<TData>
+    where
+        TData: Clone + 'static,
+    {
+        query: Weak<RefCell<Query<TData>>>,
+    }
+
+    impl<TData> LiveSink<TData>
+    where
+        TData: Clone + 'static,
+    {
+        // replace the cached data
+        pub fn emit(&self, data: TData) {
+            self.update(|_| data)
+        }
+
+        // merge into the cached data, `updater` gets `None` if the
+        // query hasn't succeeded yet
+        pub fn update(&self, updater: impl FnOnce(Option<&TData>) -> TData) {
+            // the query was garbage collected, nothing to write to
+            let Some(query) = self.query.upgrade() else {
+                return;
+            };
+            let mut query = query.borrow_mut();
+            let data = updater(query.state.data());
+            if query.is_unchanged(&data) {
+                query.state.last_updated = Some(now());
+                return;
+            }
+
+            let data = Rc::new(data);
+            query.set_state(|old| QueryState {
+                status: Status::Success(data.clone()),
+                last_updated: Some(now()),
+                ..old
+            });
+        }
+    }
+
+    pub type LiveSubscribe<TData> = Rc<dyn Fn(LiveSink<TData>) -> Box<dyn FnOnce()>>;
+
+    type LiveTeardown = Rc<RefCell<Option<Box<dyn FnOnce()>>>>;
+
+    // the subscription is started when the query gets its first subscriber
+    // and torn down once it has none left
+    #[derive(Clone)]
+    pub struct LiveQuery<TData>
+    where
+        TData: Clone + 'static,
+    {
+        subscribe: LiveSubscribe<TData>,
+        teardown: LiveTeardown,
+    }
+
+    impl<TData> LiveQuery<TData>
+    where
+        TData: Clone + 'static,
+    {
+        pub fn new(subscribe: LiveSubscribe<TData>) -> Self {
+            Self {
+                subscribe,
+                teardown: Rc::new(RefCell::new(None)),
+            }
+        }
+
+        fn start(&self, query: &Rc<RefCell<Query<TData>>>) {
+            let mut teardown = self.teardown.borrow_mut();
+            if teardown.is_none() {
+                *teardown = Some((self.subscribe)(LiveSink {
+                    query: Rc::downgrade(query),
+                }));
+            }
+        }
+
+        fn stop(&self) {
+            let teardown = self.teardown.borrow_mut().take();
+            if let Some(teardown) = teardown {
+                teardown();
+            }
+        }
+    }
+
+    impl<TData> PartialEq for LiveQuery<TData>
+    where
+        TData: Clone + 'static,
+    {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.teardown, &other.teardown)
+        }
+    }
+
+    impl<TData> Debug for LiveQuery<TData>
+    where
+        TData: Clone + 'static,
+    {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("LiveQuery")
+                .field("active", &self.teardown.borrow().is_some())
+                .finish_non_exhaustive()
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum QueryEvent {
+        CacheHit { query_key: String },
+        CacheMiss { query_key: String },
+        FetchStart { query_key: String },
+        FetchEnd { query_key: String, duration: i64 },
+        Error { query_key: String, error: String },
+        Gc { query_key: String },
+        // a query was requested with another `query_fn` than it was created
+        // with, as far as `FnPtr::is_same_fn` can tell. set
+        // `QueryOptions::query_fn_id` for functions it can't tell apart
+        QueryFnConflict { query_key: String },
+        Cancelled { query_key: String },
+        Invalidated { query_key: String },
+        QueryAdded { query_key: String },
+        // after `Gc`, or when the query was removed explicitly
+        QueryRemoved { query_key: String },
+        // the query's state changed
+        QueryUpdated { query_key: String },
+        ObserverAdded { query_key: String },
+        ObserverRemoved { query_key: String },
+    }
+
+    impl QueryEvent {
+        pub fn query_key(&self) -> &str {
+            match self {
+                QueryEvent::CacheHit { query_key }
+                | QueryEvent::CacheMiss { query_key }
+                | QueryEvent::FetchStart { query_key }
+                | QueryEvent::FetchEnd { query_key, .. }
+                | QueryEvent::Error { query_key, .. }
+                | QueryEvent::Gc { query_key }
+                | QueryEvent::QueryFnConflict { query_key }
+                | QueryEvent::Cancelled { query_key }
+                | QueryEvent::Invalidated { query_key }
+                | QueryEvent::QueryAdded { query_key }
+                | QueryEvent::QueryRemoved { query_key }
+                | QueryEvent::QueryUpdated { query_key }
+                | QueryEvent::ObserverAdded { query_key }
+                | QueryEvent::ObserverRemoved { query_key } => query_key,
+            }
+        }
+    }
+
+    // the lifecycle events as `tracing` events, `FetchEnd` carries the
+    // duration for timings
+    #[cfg(feature = "tracing")]
+    fn trace_event(event: &QueryEvent) {
+        match event {
+            QueryEvent::QueryAdded { query_key } => {
+                tracing::debug!(query_key = %query_key, "query created")
+            }
+            QueryEvent::FetchStart { query_key } => {
+                tracing::debug!(query_key = %query_key, "fetch start")
+            }
+            QueryEvent::FetchEnd {
+                query_key,
+                duration,
+            } => tracing::debug!(query_key = %query_key, duration, "fetch end"),
+            QueryEvent::Error { query_key, error } => {
+                tracing::warn!(query_key = %query_key, error = %error, "fetch failed")
+            }
+            QueryEvent::Invalidated { query_key } => {
+                tracing::debug!(query_key = %query_key, "query invalidated")
+            }
+            QueryEvent::Gc { query_key } => {
+                tracing::debug!(query_key = %query_key, "query garbage collected")
+            }
+            event => tracing::trace!(query_key = %event.query_key(), event = ?event),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct LoggedEvent {
+        pub at: i64,
+        pub event: QueryEvent,
+    }
+
+    // counters collected from the client's events since it was created or
+    // `QueryClient::reset_stats` was called
+    #[derive(Clone, PartialEq, Debug, Default)]
+    pub struct CacheStats {
+        pub fetches: u32,
+        pub cache_hits: u32,
+        pub cache_misses: u32,
+        pub errors: u32,
+        pub queries: BTreeMap<String, QueryStats>,
+    }
+
+    #[derive(Clone, PartialEq, Debug, Default)]
+    pub struct QueryStats {
+        pub fetches: u32,
+        pub cache_hits: u32,
+        pub cache_misses: u32,
+        pub errors: u32,
+        // summed over every settled fetch, in ms
+        pub total_fetch_duration: i64,
+    }
+
+    impl QueryStats {
+        pub fn average_fetch_duration(&self) -> Option<f64> {
+            (self.fetches > 0).then(|| self.total_fetch_duration as f64 / self.fetches as f64)
+        }
+    }
+
+    impl CacheStats {
+        // share of observers that found their query already cached
+        pub fn hit_rate(&self) -> Option<f64> {
+            let lookups = self.cache_hits + self.cache_misses;
+            (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+        }
+
+        fn record(&mut self, event: &QueryEvent) {
+            match event {
+                QueryEvent::CacheHit { query_key } => {
+                    self.cache_hits += 1;
+                    self.queries
+                        .entry(query_key.clone())
+                        .or_default()
+                        .cache_hits += 1;
+                }
+                QueryEvent::CacheMiss { query_key } => {
+                    self.cache_misses += 1;
+                    self.queries
+                        .entry(query_key.clone())
+                        .or_default()
+                        .cache_misses += 1;
+                }
+                QueryEvent::FetchEnd {
+                    query_key,
+                    duration,
+                } => {
+                    self.fetches += 1;
+                    let stats = self.queries.entry(query_key.clone()).or_default();
+                    stats.fetches += 1;
+                    stats.total_fetch_duration += duration;
+                }
+                QueryEvent::Error { query_key, .. } => {
+                    self.errors += 1;
+                    self.queries.entry(query_key.clone()).or_default().errors += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    const DEFAULT_EVENT_LOG_CAPACITY: usize = 100;
+
+    // ring buffer of the most recent events, oldest first
+    struct EventLog {
+        capacity: usize,
+        events: VecDeque<LoggedEvent>,
+    }
+
+    impl EventLog {
+        fn new(capacity: usize) -> Self {
+            Self {
+                capacity,
+                events: VecDeque::with_capacity(capacity),
+            }
+        }
+
+        fn push(&mut self, event: QueryEvent) {
+            if self.capacity == 0 {
+                return;
+            }
+            if self.events.len() == self.capacity {
+                self.events.pop_front();
+            }
+            self.events.push_back(LoggedEvent { at: now(), event });
+        }
+    }
+
+    // the events that refetch a stale query. `mount` only applies to
+    // queries that already have data, the first fetch always happens
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct RefetchTriggers {
+        pub mount: bool,
+        pub window_focus: bool,
+        pub reconnect: bool,
+        pub interval: bool,
+    }
+
+    impl RefetchTriggers {
+        pub const ALL: Self = Self {
+            mount: true,
+            window_focus: true,
+            reconnect: true,
+            interval: true,
+        };
+        pub const NONE: Self = Self {
+            mount: false,
+            window_focus: false,
+            reconnect: false,
+            interval: false,
+        };
+
+        pub fn union(self, other: Self) -> Self {
+            Self {
+                mount: self.mount || other.mount,
+                window_focus: self.window_focus || other.window_focus,
+                reconnect: self.reconnect || other.reconnect,
+                interval: self.interval || other.interval,
+            }
+        }
+    }
+
+    impl Default for RefetchTriggers {
+        fn default() -> Self {
+            Self::ALL
+        }
+    }
+
+    // the parts of `QueryState` a component renders, any other change
+    // doesn't rerender it. `data` compares the cached `Rc`
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct NotifyOnChangeProps {
+        pub status: bool,
+        pub data: bool,
+        pub error: bool,
+        pub fetch_status: bool,
+        pub last_updated: bool,
+        pub is_stale: bool,
+    }
+
+    impl NotifyOnChangeProps {
+        pub const ALL: Self = Self {
+            status: true,
+            data: true,
+            error: true,
+            fetch_status: true,
+            last_updated: true,
+            is_stale: true,
+        };
+        pub const NONE: Self = Self {
+            status: false,
+            data: false,
+            error: false,
+            fetch_status: false,
+            last_updated: false,
+            is_stale: false,
+        };
+
+        pub fn changed<TData>(&self, old: &QueryState<TData>, new: &QueryState<TData>) -> bool
+        where
+            TData: Clone,
+        {
+            let data = |state: &QueryState<TData>| match &state.status {
+                Status::Success(data) => Some(Rc::clone(data)),
+                _ => None,
+            };
+            let error = |state: &QueryState<TData>| match &state.status {
+                Status::Error(err) => Some(err.clone()),
+                _ => None,
+            };
+            let data_changed = match (data(old), data(new)) {
+                (Some(old), Some(new)) => !Rc::ptr_eq(&old, &new),
+                (old, new) => old.is_some() != new.is_some(),
+            };
+
+            (self.status && old.status.kind() != new.status.kind())
+                || (self.data && data_changed)
+                || (self.error && error(old) != error(new))
+                || (self.fetch_status && old.fetch_status != new.fetch_status)
+                || (self.last_updated && old.last_updated != new.last_updated)
+                || (self.is_stale && old.is_stale != new.is_stale)
+        }
+    }
+
+    impl Default for NotifyOnChangeProps {
+        fn default() -> Self {
+            Self::ALL
+        }
+    }
+
+    // what to do when a query is requested with a different `query_fn`
+    // than the one it was created with. functions are compared by
+    // `QueryOptions::query_fn_id` if set, otherwise by closure type
+    #[derive(Clone, Copy, PartialEq, Debug, Default)]
+    pub enum QueryFnConflict {
+        // keep the first registered function, emitting a
+        // `QueryEvent::QueryFnConflict` in debug builds
+        #[default]
+        Warn,
+        // replace the stored function with the latest one
+        Replace,
+    }
+
+    // artificial latency (ms) and failure rate (0.0 - 1.0) applied to every
+    // query execution, to exercise loading and error states during
+    // development. ignored in release builds
+    #[derive(Clone, Copy, PartialEq, Debug, Default)]
+    pub struct DevFaults {
+        pub latency: i32,
+        pub failure_rate: f64,
+    }
+
+    // extension surface for persisters, loggers, broadcasters and metrics.
+    // every hook defaults to a no-op, so plugins only implement what they
+    // need. data is passed type-erased, plugins that only handle some data
+    // types downcast it
+    pub trait QueryPlugin {
+        fn on_query_added(&self, _query: &dyn AnyQuery) {}
+        fn on_fetch(&self, _query_key: &str) {}
+        fn on_success(&self, _query_key: &str, _data: &dyn Any) {}
+        fn on_error(&self, _query_key: &str, _error: &str) {}
+        fn on_gc(&self, _query_key: &str) {}
+        // called once the query's data is gone: it was garbage collected,
+        // evicted, removed with `remove_queries` or reset with
+        // `reset_queries`, e.g. to drop what a persister stored for it
+        fn on_query_removed(&self, _query_key: &str) {}
+        // called before the query function runs. a returned future that
+        // resolves to a result is used instead of fetching, e.g. by a plugin
+        // that got the result from elsewhere. only the first plugin
+        // returning a future is asked. a result holding anything but the
+        // query's `data_type` is ignored
+        fn intercept_fetch(
+            &self,
+            _query_key: &str,
+            _data_type: TypeId,
+        ) -> Option<InterceptedFetch> {
+            None
+        }
+        // called once a mutation has settled
+        fn on_mutation(&self, _mutation: &Mutation) {}
+        // called when a `QueryClientProvider` using the client mounts. the
+        // returned function runs when it unmounts
+        fn on_mount(&self, _client: &QueryClient) -> Box<dyn FnOnce()> {
+            Box::new(|| ())
+        }
+    }
+
+    pub type InterceptedFetch = Pin<Box<dyn Future<Output = Option<QueryResult<Box<dyn Any>>>>>>;
+
+    #[derive(Clone)]
+    struct Plugins(Rc<Vec<Rc<dyn QueryPlugin>>>);
+
+    impl Plugins {
+        fn iter(&self) -> impl Iterator<Item = &Rc<dyn QueryPlugin>> {
+            self.0.iter()
+        }
+    }
+
+    // produces a query's data and `last_updated` when it's first requested
+    pub(crate) type Seed<TData> = Box<dyn FnOnce() -> Option<(TData, Option<i64>)>>;
+
+    // `Seed`s by query key and data type
+    type Seeds = Rc<RefCell<HashMap<(String, TypeId), Box<dyn Any>>>>;
+
+    // estimate the size of a query's data, by data type
+    pub(crate) type Sizers = Rc<RefCell<HashMap<TypeId, Rc<dyn Fn(&dyn Any) -> Option<usize>>>>>;
+
+    // what `QueryClient::cache_info` reports for a query
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct QueryInfo {
+        pub query_key: String,
+        pub data_type: &'static str,
+        pub status: QueryStatus,
+        pub observers: usize,
+        pub last_updated: Option<i64>,
+        pub last_used: i64,
+        pub is_stale: bool,
+        // length of the data serialized as JSON, only for data types
+        // registered with `QueryClient::measure_size`
+        pub size: Option<usize>,
+    }
+
+    pub struct QueryClient {
+        pub(crate) queries: QueryCache,
+        subscribers: Rc<RefCell<Vec<Callback<()>>>>,
+        on_event: Option<Callback<QueryEvent>>,
+        event_subscribers: Rc<RefCell<Vec<Callback<QueryEvent>>>>,
+        // events waiting for `deliver_events`
+        pending_events: Rc<RefCell<Vec<QueryEvent>>>,
+        on_query_success: Option<Callback<String>>,
+        on_query_error: Option<Callback<(String, String)>>,
+        query_fn_conflict: QueryFnConflict,
+        dev_faults: Option<DevFaults>,
+        plugins: Plugins,
+        seeds: Seeds,
+        mutation_defaults: Rc<RefCell<HashMap<String, Rc<dyn Any>>>>,
+        event_log: Rc<RefCell<EventLog>>,
+        stats: Rc<RefCell<CacheStats>>,
+        refetch_triggers: RefetchTriggers,
+        pub(crate) default_options: DefaultQueryOptions,
+        mutation_cache: MutationCache,
+        pub(crate) logger: Rc<dyn Logger>,
+        notify_manager: NotifyManager,
+        fetch_queue: FetchQueue,
+        max_queries: Option<usize>,
+        pub(crate) sizers: Sizers,
+    }
+
+    // limits how many query functions run at once, the other fetches wait
+    // in line for a slot
+    #[derive(Clone, Default)]
+    struct FetchQueue {
+        limit: Option<usize>,
+        running: Rc<Cell<usize>>,
+        waiting: Rc<RefCell<VecDeque<(FetchPriority, js_sys::Function)>>>,
+    }
+
+    impl FetchQueue {
+        async fn acquire(&self, priority: FetchPriority) -> FetchSlot {
+            if let Some(limit) = self.limit {
+                if self.running.get() < limit {
+                    self.running.set(self.running.get() + 1);
+                } else {
+                    let mut resolve = None;
+                    let promise = js_sys::Promise::new(&mut |res, _| resolve = Some(res));
+                    let resolve = resolve.expect("`Promise` executor runs synchronously");
+                    {
+                        let mut waiting = self.waiting.borrow_mut();
+                        let at = waiting
+                            .iter()
+                            .position(|(waiting, _)| *waiting < priority)
+                            .unwrap_or(waiting.len());
+                        waiting.insert(at, (priority, resolve));
+                    }
+                    // the released slot is handed over, `running` stays
+                    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+                }
+            }
+            FetchSlot(self.clone())
+        }
+    }
+
+    // frees its slot for the next waiting fetch when dropped
+    struct FetchSlot(FetchQueue);
+
+    impl Drop for FetchSlot {
+        fn drop(&mut self) {
+            let queue = &self.0;
+            if queue.limit.is_none() {
+                return;
+            }
+            let next = queue.waiting.borrow_mut().pop_front();
+            match next {
+                Some((_, next)) => {
+                    let _ = next.call0(&wasm_bindgen::JsValue::NULL);
+                }
+                None => queue.running.set(queue.running.get() - 1),
+            }
+        }
+    }
+
+    // collects the callbacks to notify and runs each of them once in a
+    // microtask, so the consecutive state updates of a fetch rerender a
+    // component once
+    #[derive(Clone, Default)]
+    struct NotifyManager {
+        pending: Rc<RefCell<Vec<Callback<()>>>>,
+        scheduled: Rc<Cell<bool>>,
+    }
+
+    impl NotifyManager {
+        fn schedule(&self, callback: &Callback<()>) {
+            let mut pending = self.pending.borrow_mut();
+            if !pending.contains(callback) {
+                pending.push(callback.clone());
+            }
+            std::mem::drop(pending);
+
+            if self.scheduled.replace(true) {
+                return;
+            }
+            let manager = self.clone();
+            wasm_bindgen_futures::spawn_local(async move { manager.flush() });
+        }
+
+        fn flush(&self) {
+            self.scheduled.set(false);
+            // callbacks notifying again get batched into the next flush
+            let pending = std::mem::take(&mut *self.pending.borrow_mut());
+            for callback in pending {
+                callback.emit(());
+            }
+        }
+    }
+
+    // queries hold a clone of their client, so comparing or printing the
+    // cache contents would recurse
+    impl PartialEq for QueryClient {
+        fn eq(&self, other: &Self) -> bool {
+            self.queries == other.queries
+        }
+    }
+
+    impl Debug for QueryClient {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("QueryClient")
+                .field(
+                    "queries",
+                    &self.queries.try_borrow().map(|queries| queries.len()),
+                )
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Clone for QueryClient {
+        fn clone(&self) -> Self {
+            Self {
+                queries: self.queries.clone(),
+                subscribers: Rc::clone(&self.subscribers),
+                on_event: self.on_event.clone(),
+                event_subscribers: Rc::clone(&self.event_subscribers),
+                pending_events: Rc::clone(&self.pending_events),
+                on_query_success: self.on_query_success.clone(),
+                on_query_error: self.on_query_error.clone(),
+                query_fn_conflict: self.query_fn_conflict,
+                dev_faults: self.dev_faults,
+                plugins: self.plugins.clone(),
+                seeds: Rc::clone(&self.seeds),
+                mutation_defaults: Rc::clone(&self.mutation_defaults),
+                event_log: Rc::clone(&self.event_log),
+                stats: Rc::clone(&self.stats),
+                refetch_triggers: self.refetch_triggers,
+                default_options: self.default_options.clone(),
+                mutation_cache: self.mutation_cache.clone(),
+                logger: Rc::clone(&self.logger),
+                notify_manager: self.notify_manager.clone(),
+                fetch_queue: self.fetch_queue.clone(),
+                max_queries: self.max_queries,
+                sizers: Rc::clone(&self.sizers),
+            }
+        }
+    }
+
+    impl QueryClient {
+        pub fn new() -> Self {
+            Self {
+                queries: QueryCache::default(),
+                subscribers: Rc::new(RefCell::new(vec![])),
+                on_event: None,
+                event_subscribers: Rc::new(RefCell::new(vec![])),
+                pending_events: Rc::new(RefCell::new(vec![])),
+                on_query_success: None,
+                on_query_error: None,
+                query_fn_conflict: QueryFnConflict::default(),
+                dev_faults: None,
+                plugins: Plugins(Rc::new(vec![
+                    Rc::new(FocusRefetch),
+                    Rc::new(ReconnectRefetch),
+                ])),
+                seeds: Rc::new(RefCell::new(HashMap::new())),
+                mutation_defaults: Rc::new(RefCell::new(HashMap::new())),
+                event_log: Rc::new(RefCell::new(EventLog::new(DEFAULT_EVENT_LOG_CAPACITY))),
+                stats: Rc::new(RefCell::new(CacheStats::default())),
+                refetch_triggers: RefetchTriggers::default(),
+                default_options: DefaultQueryOptions::default(),
+                mutation_cache: MutationCache::new(),
+                logger: default_logger(),
+                notify_manager: NotifyManager::default(),
+                fetch_queue: FetchQueue::default(),
+                max_queries: None,
+                sizers: Rc::new(RefCell::new(HashMap::new())),
+            }
+        }
+
+        // plugins run in the order they were registered
+        pub fn with_plugin(mut self, plugin: impl QueryPlugin + 'static) -> Self {
+            Rc::make_mut(&mut self.plugins.0).push(Rc::new(plugin));
+            self
+        }
+
+        // drops every registered plugin, including the built-in ones,
+        // e.g. to swap `FocusRefetch` for a custom implementation
+        pub fn without_plugins(mut self) -> Self {
+            self.plugins = Plugins(Rc::new(vec![]));
+            self
+        }
+
+        // sink for structured cache events, e.g. to forward them to analytics
+        pub fn on_event(mut self, callback: Callback<QueryEvent>) -> Self {
+            self.on_event = Some(callback);
+            self
+        }
+
+        // called with the key of every query that fetched successfully
+        pub fn on_query_success(mut self, callback: Callback<String>) -> Self {
+            self.on_query_success = Some(callback);
+            self
+        }
+
+        // called with the key and error of every query whose fetch failed
+        // (after its retries), e.g. to show a toast or report it
+        pub fn on_query_error(mut self, callback: Callback<(String, String)>) -> Self {
+            self.on_query_error = Some(callback);
+            self
+        }
+
+        // replaces the default logger, e.g. `NoopLogger` to silence the
+        // `logging` feature in a single client. without the feature nothing
+        // is logged, whatever the logger
+        pub fn with_logger(mut self, logger: impl Logger + 'static) -> Self {
+            self.logger = Rc::new(logger);
+            self
+        }
+
+        pub fn query_fn_conflict(mut self, policy: QueryFnConflict) -> Self {
+            self.query_fn_conflict = policy;
+            self
+        }
+
+        pub fn dev_faults(mut self, faults: DevFaults) -> Self {
+            self.dev_faults = Some(faults);
+            self
+        }
+
+        // default triggers for every query, `QueryOptions::refetch_triggers`
+        // overrides them per query
+        pub fn refetch_triggers(mut self, triggers: RefetchTriggers) -> Self {
+            self.refetch_triggers = triggers;
+            self
+        }
+
+        // used by every query for the options its `QueryOptions` leave unset
+        pub fn default_options(mut self, options: DefaultQueryOptions) -> Self {
+            self.default_options = options;
+            self
+        }
+
+        // how many query functions may run at once across the client, the
+        // other fetches wait until one of them settles. unlimited by default
+        pub fn max_concurrent_fetches(mut self, limit: usize) -> Self {
+            self.fetch_queue = FetchQueue {
+                limit: Some(limit.max(1)),
+                ..FetchQueue::default()
+            };
+            self
+        }
+
+        // caps the number of cached queries regardless of `cache_time`, new
+        // queries evict the least recently used inactive ones. mounted
+        // queries are never evicted, so the cache can still exceed it
+        pub fn max_queries(mut self, max_queries: usize) -> Self {
+            self.max_queries = Some(max_queries);
+            self
+        }
+
+        // how many events `event_log` keeps around, 0 disables the log
+        pub fn event_log_capacity(mut self, capacity: usize) -> Self {
+            self.event_log = Rc::new(RefCell::new(EventLog::new(capacity)));
+            self
+        }
+
+        // the last events across every query, oldest first, e.g. to attach
+        // to an error report
+        pub fn event_log(&self) -> Vec<LoggedEvent> {
+            self.event_log.borrow().events.iter().cloned().collect()
+        }
+
+        // the logged events for a single query key
+        pub fn event_log_for(&self, query_key: &str) -> Vec<LoggedEvent> {
+            self.event_log
+                .borrow()
+                .events
+                .iter()
+                .filter(|logged| logged.event.query_key() == query_key)
+                .cloned()
+                .collect()
+        }
+
+        pub fn stats(&self) -> CacheStats {
+            self.stats.borrow().clone()
+        }
+
+        pub fn reset_stats(&self) {
+            *self.stats.borrow_mut() = CacheStats::default();
+        }
+
+        // a snapshot of every cached query, e.g. to find what takes up
+        // memory before picking `max_queries` or `cache_time`
+        pub fn cache_info(&self) -> Vec<QueryInfo> {
+            let sizers = self.sizers.borrow();
+            self.queries
+                .borrow()
+                .values()
+                .map(|query| {
+                    let size = sizers
+                        .get(&query.data_type())
+                        .zip(query.data())
+                        .and_then(|(size_of, data)| size_of(&*data));
+                    QueryInfo {
+                        query_key: query.query_key(),
+                        data_type: query.data_type_name(),
+                        status: query.status().status,
+                        observers: query.subscriber_count(),
+                        last_updated: query.last_updated(),
+                        last_used: query.last_used(),
+                        is_stale: query.is_stale(),
+                        size,
+                    }
+                })
+                .collect()
+        }
+
+        // how long settled mutations stay in the mutation cache, and so in
+        // `use_mutation_state`, 5 minutes by default
+        pub fn mutation_gc_time(mut self, gc_time: impl Into<GcTime>) -> Self {
+            self.mutation_cache.gc_time = gc_time.into().as_millis();
+            self
+        }
+
+        // for inspecting the cached queries, e.g. from tooling
+        pub fn query_cache(&self) -> QueryCache {
+            self.queries.clone()
+        }
+
+        pub fn mutation_cache(&self) -> MutationCache {
+            self.mutation_cache.clone()
+        }
+
+        pub(crate) fn mount_plugins(&self) -> Vec<Box<dyn FnOnce()>> {
+            let mut cleanups = self
+                .plugins
+                .iter()
+                .map(|plugin| plugin.on_mount(self))
+                .collect::<Vec<_>>();
+
+            let on_mutation = {
+                let plugins = self.plugins.clone();
+                Callback::from(move |mutation: Mutation| {
+                    if mutation.is_settled() {
+                        for plugin in plugins.iter() {
+                            plugin.on_mutation(&mutation);
+                        }
+                    }
+                })
+            };
+            self.mutation_cache.subscribe(on_mutation.clone());
+            let mutation_cache = self.mutation_cache.clone();
+            cleanups.push(Box::new(move || mutation_cache.unsubscribe(on_mutation)));
+
+            cleanups
+        }
+
+        // the cached query for `query_key`, if it holds `TData`
+        pub(crate) fn find_query<TData>(&self, query_key: &str) -> Option<Rc<RefCell<Query<TData>>>>
+        where
+            TData: Clone + 'static,
+        {
+            let query = self.queries.borrow().get(query_key).cloned()?;
+
+            query.into_any().downcast().ok()
+        }
+
+        fn is_cached(&self, query_key: &str) -> bool {
+            self.queries.borrow().contains_key(query_key)
+        }
+
+        // applies the seed right away if the query is already cached (unless
+        // the cached data is newer), otherwise when it's first requested.
+        // seeds only apply to queries holding `TData`
+        pub(crate) fn seed_query<TData>(&self, query_key: String, seed: Seed<TData>)
+        where
+            TData: Clone + 'static,
+        {
+            match self.find_query::<TData>(&query_key) {
+                Some(query) => {
+                    if let Some((data, updated_at)) = seed() {
+                        let data = Rc::new(data);
+                        let mut query = query.borrow_mut();
+                        if updated_at >= query.state.last_updated {
+                            query.set_state(|old| QueryState {
+                                status: Status::Success(data.clone()),
+                                last_updated: updated_at,
+                                ..old
+                            });
+                        }
+                    }
+                }
+                // cached with a different data type
+                None if self.is_cached(&query_key) => {}
+                None => {
+                    self.seeds
+                        .borrow_mut()
+                        .insert((query_key, TypeId::of::<TData>()), Box::new(seed));
+                }
+            }
+        }
+
+        fn emit_event(&self, event: QueryEvent) {
+            #[cfg(feature = "tracing")]
+            trace_event(&event);
+            self.stats.borrow_mut().record(&event);
+            self.event_log.borrow_mut().push(event.clone());
+            if self.on_event.is_none() && self.event_subscribers.borrow().is_empty() {
+                return;
+            }
+
+            // events are mostly emitted with the query or the cache
+            // borrowed, the listeners get them once that's over
+            let mut pending = self.pending_events.borrow_mut();
+            pending.push(event);
+            // a delivery is already scheduled
+            if pending.len() > 1 {
+                return;
+            }
+            std::mem::drop(pending);
+            let client = self.clone();
+            self.notify_manager
+                .schedule(&Callback::from(move |_| client.deliver_events()));
+        }
+
+        fn deliver_events(&self) {
+            let events = std::mem::take(&mut *self.pending_events.borrow_mut());
+            let subscribers = self.event_subscribers.borrow().clone();
+            for event in events {
+                for subscriber in &subscribers {
+                    subscriber.emit(event.clone());
+                }
+                if let Some(on_event) = &self.on_event {
+                    on_event.emit(event)
+                }
+            }
+        }
+
+        // called with every `QueryEvent`, e.g. by external tooling. events
+        // are delivered in order in a microtask after they happened, when
+        // nothing in the cache is borrowed anymore
+        pub fn subscribe_events(&self, callback: Callback<QueryEvent>) {
+            self.event_subscribers.borrow_mut().push(callback);
+        }
+
+        pub fn unsubscribe_events(&self, callback: Callback<QueryEvent>) {
+            self.event_subscribers
+                .borrow_mut()
+                .retain(|subscriber| subscriber.clone() != callback);
+        }
+
+        // picked up by `use_mutation_key(mutation_key)`
+        pub fn set_mutation_defaults<TVariables, TMutationData, TError>(
+            &self,
+            mutation_key: &str,
+            defaults: MutationDefaults<TVariables, TMutationData, TError>,
+        ) where
+            TVariables: 'static,
+            TMutationData: 'static,
+            TError: 'static,
+        {
+            self.mutation_defaults
+                .borrow_mut()
+                .insert(mutation_key.to_string(), Rc::new(defaults));
+        }
+
+        pub fn get_mutation_defaults<TVariables, TMutationData, TError>(
+            &self,
+            mutation_key: &str,
+        ) -> Option<Rc<MutationDefaults<TVariables, TMutationData, TError>>>
+        where
+            TVariables: 'static,
+            TMutationData: 'static,
+            TError: 'static,
+        {
+            let defaults = self.mutation_defaults.borrow().get(mutation_key).cloned()?;
+            defaults.downcast().ok()
+        }
+
+        // the cached queries `filters` match
+        fn matching(&self, filters: impl Into<QueryFilters>) -> Vec<Rc<dyn AnyQuery>> {
+            let filters = filters.into();
+            self.queries
+                .borrow()
+                .values()
+                .filter(|&query| filters.matches(&**query))
+                .cloned()
+                .collect()
+        }
+
+        // marks the queries stale and refetches the mounted ones, unmounted
+        // queries refetch once they're used again. like `refetch_queries`
+        // the refetches run concurrently
+        pub async fn invalidate_queries(&self, filters: impl Into<QueryFilters>) {
+            let refetches = self
+                .matching(filters)
+                .iter()
+                .filter_map(|query| query.invalidate())
+                .collect::<Vec<_>>();
+
+            join_all(refetches).await
+        }
+
+        // `invalidate_queries` for every key starting with `prefix`
+        pub async fn invalidate_queries_by_prefix(&self, prefix: &str) {
+            self.invalidate_queries(QueryFilters::prefix(prefix)).await
+        }
+
+        // `invalidate_queries` for every key below `key` in its hierarchy,
+        // including `key` itself
+        pub async fn invalidate_queries_by_key(&self, key: &QueryKey) {
+            self.invalidate_queries(key).await
+        }
+
+        // refetches every query matching `filters` regardless of
+        // staleness, e.g. from a "Refresh" button. they run concurrently,
+        // the future resolves once all of them have settled
+        pub async fn refetch_queries(&self, filters: impl Into<QueryFilters>) {
+            let refetches = self
+                .matching(filters)
+                .iter()
+                .map(|query| query.refetch())
+                .collect::<Vec<_>>();
+
+            join_all(refetches).await
+        }
+
+        // puts every query matching `filters` back into the state it
+        // was created with (holding its `initial_data`, if any) and
+        // refetches the mounted ones concurrently, e.g. for "reset filters"
+        pub async fn reset_queries(&self, filters: impl Into<QueryFilters>) {
+            let refetches = self
+                .matching(filters)
+                .iter()
+                .filter_map(|query| {
+                    let refetch = query.reset();
+                    for plugin in self.plugins.iter() {
+                        plugin.on_query_removed(&query.query_key());
+                    }
+                    refetch
+                })
+                .collect::<Vec<_>>();
+
+            join_all(refetches).await
+        }
+
+        // aborts the fetches of every query matching `filters`. the
+        // `AbortSignal` passed to their query functions fires, so requests
+        // made with it are aborted too, and the queries keep their state
+        pub fn cancel_queries(&self, filters: impl Into<QueryFilters>) {
+            for query in self.matching(filters) {
+                query.cancel()
+            }
+        }
+
+        // drops the queries matching `filters` from the cache right
+        // away, e.g. after logging out. mounted ones start over from
+        // `Loading`, the others once they're used again
+        pub fn remove_queries(&self, filters: impl Into<QueryFilters>) {
+            let filters = filters.into();
+            let removed = {
+                let mut queries = self.queries.borrow_mut();
+                let (removed, kept) = std::mem::take(&mut *queries)
+                    .into_iter()
+                    .partition::<BTreeMap<_, _>, _>(|(_, query)| filters.matches(&**query));
+                *queries = kept;
+                removed
+            };
+
+            for query in removed.into_values() {
+                let query_key = query.query_key();
+                query.detach();
+                for plugin in self.plugins.iter() {
+                    plugin.on_query_removed(&query_key);
+                }
+                self.emit_event(QueryEvent::QueryRemoved { query_key });
+            }
+            self.notify();
+        }
+
+        // refetches every query currently in `Status::Error` matching
+        // `filters`, concurrently
+        pub async fn retry_errored(&self, filters: impl Into<QueryFilters>) {
+            let refetches = self
+                .matching(filters)
+                .iter()
+                .filter(|query| query.status().status == QueryStatus::Error)
+                .map(|query| query.refetch())
+                .collect::<Vec<_>>();
+
+            join_all(refetches).await
+        }
+
+        // a key can only be cached with one data type at a time, requesting
+        // it with another one panics
+        pub(crate) fn get_query<TData>(
+            &mut self,
+            options: &QueryOptions<TData>,
+        ) -> Rc<RefCell<Query<TData>>>
+        where
+            TData: Clone + 'static,
+        {
+            let query_key = options.query_key.clone();
+            let query = self.queries.borrow().get(&query_key).cloned();
+
+            if let Some(query) = query {
+                debug_log!(self, "query found {:?}", query_key);
+
+                let data_type = query.data_type_name();
+                let query = query
+                    .into_any()
+                    .downcast::<RefCell<Query<TData>>>()
+                    .unwrap_or_else(|_| {
+                        panic!(
+                            "Query {:?} is cached as `{}`, requested as `{}`",
+                            query_key,
+                            data_type,
+                            std::any::type_name::<TData>()
+                        )
+                    });
+
+                let conflicting = !query.borrow().query_fn.is_same_fn(&options.query_fn);
+                if conflicting {
+                    match self.query_fn_conflict {
+                        QueryFnConflict::Warn => {
+                            debug_log!(
+                                self,
+                                "conflicting `query_fn` registered for {:?}",
+                                query_key
+                            );
+                            #[cfg(debug_assertions)]
+                            self.emit_event(QueryEvent::QueryFnConflict {
+                                query_key: query_key.clone(),
+                            });
+                        }
+                        QueryFnConflict::Replace => {
+                            query.borrow_mut().query_fn = options.query_fn.clone();
+                        }
+                    }
+                }
+                query.borrow_mut().last_used = now();
+
+                self.emit_event(QueryEvent::CacheHit { query_key });
+                query
+            } else {
+                self.emit_event(QueryEvent::CacheMiss {
+                    query_key: query_key.clone(),
+                });
+                let mut query = create_query(self.clone(), options);
+                // stays `Idle` until something fetches it
+                if options.enabled {
+                    query.state.status = Status::Loading;
+                } else {
+                    query.state.fetch_status = FetchStatus::Idle;
+                }
+
+                let seed = self
+                    .seeds
+                    .borrow_mut()
+                    .remove(&(query.query_key.clone(), TypeId::of::<TData>()))
+                    .and_then(|seed| seed.downcast::<Seed<TData>>().ok());
+                let seeded = seed.and_then(|seed| seed()).or_else(|| {
+                    let (initial_data, updated_at) = options.initial_data.as_ref()?;
+                    Some((initial_data.get(), Some(updated_at.unwrap_or_else(now))))
+                });
+                if let Some((data, updated_at)) = seeded {
+                    query.state.status = Status::Success(Rc::new(data));
+                    query.state.fetch_status = FetchStatus::Idle;
+                    query.state.last_updated = updated_at;
+                }
+                let query = Rc::new(RefCell::new(query));
+                query.borrow_mut().this = QueryCell(Rc::downgrade(&query));
+                self.queries
+                    .borrow_mut()
+                    .insert(query_key.clone(), Rc::clone(&query) as Rc<dyn AnyQuery>);
+
+                for plugin in self.plugins.iter() {
+                    plugin.on_query_added(&*query);
+                }
+                self.emit_event(QueryEvent::QueryAdded {
+                    query_key: query_key.clone(),
+                });
+                self.evict_unused(&query_key);
+
+                query
+            }
+        }
+
+        // drops the least recently used inactive queries while the cache
+        // holds more than `max_queries`, never the query `keep` was just
+        // added for
+        fn evict_unused(&self, keep: &str) {
+            let Some(max_queries) = self.max_queries else {
+                return;
+            };
+            let evicted = {
+                let mut queries = self.queries.borrow_mut();
+                let excess = queries.len().saturating_sub(max_queries);
+                if excess == 0 {
+                    return;
+                }
+                let mut unused = queries
+                    .iter()
+                    .filter(|(query_key, query)| {
+                        *query_key != keep && query.subscriber_count() == 0
+                    })
+                    .map(|(query_key, query)| (query.last_used(), query_key.clone()))
+                    .collect::<Vec<_>>();
+                unused.sort();
+                unused
+                    .into_iter()
+                    .take(excess)
+                    .filter_map(|(_, query_key)| queries.remove(&query_key))
+                    .collect::<Vec<_>>()
+            };
+
+            for query in evicted {
+                let query_key = query.query_key();
+                query.detach();
+                for plugin in self.plugins.iter() {
+                    plugin.on_gc(&query_key);
+                    plugin.on_query_removed(&query_key);
+                }
+                self.emit_event(QueryEvent::Gc {
+                    query_key: query_key.clone(),
+                });
+                self.emit_event(QueryEvent::QueryRemoved { query_key });
+            }
+        }
+
+        // `None` if the key isn't cached, or holds something other than
+        // `TData`
+        pub fn get_query_data<TData>(&self, query_key: &str) -> Option<TData>
+        where
+            TData: Clone + 'static,
+        {
+            self.get_query_data_with_updated_at(query_key)
+                .map(|(data, _)| data)
+        }
+
+        // writes `data` to the query and notifies its subscribers. if the
+        // key isn't cached yet it's used once the query is first requested
+        pub fn set_query_data<TData>(&self, query_key: &str, data: TData)
+        where
+            TData: Clone + 'static,
+        {
+            self.update_query_data(query_key, move |_| data)
+        }
+
+        // like `set_query_data`, computing the data from the cached value,
+        // e.g. for optimistic updates. does nothing if the key is cached
+        // with a different data type
+        pub fn update_query_data<TData>(
+            &self,
+            query_key: &str,
+            updater: impl FnOnce(Option<&TData>) -> TData + 'static,
+        ) where
+            TData: Clone + 'static,
+        {
+            match self.find_query::<TData>(query_key) {
+                Some(query) => {
+                    let mut query = query.borrow_mut();
+                    let data = Rc::new(updater(query.state.data()));
+                    query.set_state(|old| QueryState {
+                        status: Status::Success(data.clone()),
+                        last_updated: Some(now()),
+                        ..old
+                    });
+                }
+                None => self.seed_query(
+                    query_key.to_string(),
+                    Box::new(move || Some((updater(None), Some(now())))),
+                ),
+            }
+        }
+
+        // the cached data with the time it was set, if the query succeeded
+        pub fn get_query_data_with_updated_at<TData>(&self, query_key: &str) -> Option<(TData, i64)>
+        where
+            TData: Clone + 'static,
+        {
+            let query = self.find_query::<TData>(query_key)?;
+            let query = query.borrow();
+            match (&query.state.status, query.state.last_updated) {
+                (Status::Success(data), Some(updated_at)) => Some(((**data).clone(), updated_at)),
+                _ => None,
+            }
+        }
+
+        // the data of every successful query matching `filters` that holds
+        // `TData`, keyed by query key
+        pub fn get_queries_data<TData>(
+            &self,
+            filters: impl Into<QueryFilters>,
+        ) -> Vec<(String, TData)>
+        where
+            TData: Clone + 'static,
+        {
+            self.matching_data::<TData>(filters)
+                .into_iter()
+                .filter_map(|query| {
+                    let query = query.borrow();
+                    match &query.state.status {
+                        Status::Success(data) => Some((query.query_key.clone(), (**data).clone())),
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+
+        // rewrites the data of every successful query matching `filters`
+        // that holds `TData`, e.g. patching all `post:*` entries after a
+        // bulk edit. each updated query notifies its subscribers once.
+        // returns how many queries were updated
+        pub fn set_queries_data<TData>(
+            &self,
+            filters: impl Into<QueryFilters>,
+            updater: impl Fn(&TData) -> TData,
+        ) -> usize
+        where
+            TData: Clone + 'static,
+        {
+            let mut updated = 0;
+            for query in self.matching_data::<TData>(filters) {
+                let mut query = query.borrow_mut();
+                let data = match &query.state.status {
+                    Status::Success(data) => Rc::new(updater(data)),
+                    _ => continue,
+                };
+                query.set_state(|old| QueryState {
+                    status: Status::Success(data.clone()),
+                    last_updated: Some(now()),
+                    ..old
+                });
+                updated += 1;
+            }
+            updated
+        }
+
+        fn matching_data<TData>(
+            &self,
+            filters: impl Into<QueryFilters>,
+        ) -> Vec<Rc<RefCell<Query<TData>>>>
+        where
+            TData: Clone + 'static,
+        {
+            self.matching(filters)
+                .into_iter()
+                .filter_map(|query| query.into_any().downcast().ok())
+                .collect()
+        }
+
+        // how many queries matching `filters` are fetching right now,
+        // e.g. for a `beforeunload` guard
+        pub fn is_fetching(&self, filters: impl Into<QueryFilters>) -> usize {
+            self.matching(filters.into().fetching(true)).len()
+        }
+
+        // idle and not fetching if the key isn't cached
+        pub fn get_query_status(&self, query_key: &str) -> QueryStatusState {
+            self.queries
+                .borrow()
+                .get(query_key)
+                .map(|query| query.status())
+                .unwrap_or(QueryStatusState {
+                    status: QueryStatus::Idle,
+                    fetch_status: FetchStatus::Idle,
+                })
+        }
+
+        pub fn notify(&self) {
+            for subscriber in (*self.subscribers).borrow().iter() {
+                self.notify_manager.schedule(subscriber)
+            }
+        }
+
+        pub fn subscribe(&mut self, callback: Callback<()>) {
+            (*self.subscribers).borrow_mut().push(callback);
+        }
+
+        pub fn unsubscribe(&mut self, callback: Callback<()>) {
+            (*self.subscribers)
+                .borrow_mut()
+                .retain(|subscriber| subscriber.clone() != callback)
+        }
+    }
+
+    impl Default for QueryClient {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum Status<TData>
+    where
+        TData: Clone,
+    {
+        Idle,
+        Loading,
+        // shared with every observer and rerender instead of cloned
+        Success(Rc<TData>),
+        Error(String),
+    }
+
+    // `Status` without the data
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum QueryStatus {
+        Idle,
+        Loading,
+        Success,
+        Error,
+    }
+
+    impl<TData> Status<TData>
+    where
+        TData: Clone,
+    {
+        pub fn kind(&self) -> QueryStatus {
+            match self {
+                Status::Idle => QueryStatus::Idle,
+                Status::Loading => QueryStatus::Loading,
+                Status::Success(_) => QueryStatus::Success,
+                Status::Error(_) => QueryStatus::Error,
+            }
+        }
+
+        pub fn data(&self) -> Option<&TData> {
+            match self {
+                Status::Success(data) => Some(data),
+                _ => None,
+            }
+        }
+
+        pub fn error(&self) -> Option<&String> {
+            match self {
+                Status::Error(err) => Some(err),
+                _ => None,
+            }
+        }
+
+        pub fn is_idle(&self) -> bool {
+            matches!(self, Status::Idle)
+        }
+
+        pub fn is_loading(&self) -> bool {
+            matches!(self, Status::Loading)
+        }
+
+        pub fn is_success(&self) -> bool {
+            matches!(self, Status::Success(_))
+        }
+
+        pub fn is_error(&self) -> bool {
+            matches!(self, Status::Error(_))
+        }
+    }
+
+    // whether a fetch is running, apart from what the query holds. a
+    // query can be `Success` and `Fetching` during a background refetch,
+    // or `Loading` and `Paused` when its first fetch waits to go online
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+    pub enum FetchStatus {
+        #[default]
+        Idle,
+        Fetching,
+        // waiting for the browser to come back online, see
+        // `QueryOptions::network_mode`
+        Paused,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct QueryStatusState {
+        pub status: QueryStatus,
+        pub fetch_status: FetchStatus,
+    }
+
+    impl QueryStatusState {
+        pub fn is_fetching(&self) -> bool {
+            self.fetch_status == FetchStatus::Fetching
+        }
+    }
+
+    // resolves `done` once the fetch settled
+    #[derive(Clone, Debug)]
+    struct InFlight {
+        done: js_sys::Promise,
+    }
+
+    impl PartialEq for InFlight {
+        fn eq(&self, other: &Self) -> bool {
+            let done: &wasm_bindgen::JsValue = self.done.as_ref();
+            let other: &wasm_bindgen::JsValue = other.done.as_ref();
+            done == other
+        }
+    }
+
+    // the `Rc` a cached query lives in, for the fetches it starts itself
+    #[derive(Clone, Debug)]
+    pub(crate) struct QueryCell<TData>(Weak<RefCell<Query<TData>>>)
+    where
+        TData: Clone + 'static;
+
+    impl<TData> PartialEq for QueryCell<TData>
+    where
+        TData: Clone + 'static,
+    {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.ptr_eq(&other.0)
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct Query<TData>
+    where
+        TData: Clone + 'static,
+    {
+        // change to lifetime reference
+        client: QueryClient,
+        pub state: QueryState<TData>,
+        pub query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
+        pub subscribers: Vec<QuerySubscriber>,
+        pub query_key: String,
+        // in ms, `None` if it's never garbage collected
+        pub cache_time: Option<i32>,
+        // the pending garbage collection, shared with its timer which
+        // clears it once it fired
+        timeout: Rc<Cell<Option<i32>>>,
+        live: Option<LiveQuery<TData>>,
+        is_equal: Option<DataEq<TData>>,
+        retry: Retry,
+        retry_delay: RetryDelay,
+        // per attempt, in ms
+        fetch_timeout: Option<i32>,
+        // aborts the running fetch
+        abort: Rc<RefCell<Option<web_sys::AbortController>>>,
+        // the running fetch, awaited by the fetches started meanwhile
+        in_flight: Rc<RefCell<Option<InFlight>>>,
+        this: QueryCell<TData>,
+        meta: Option<QueryMeta>,
+        network_mode: NetworkMode,
+        // may be lowered for a single fetch, see `QueryClient::prefetch_query`
+        priority: FetchPriority,
+        // no longer in the cache, see `QueryClient::remove_queries`
+        removed: bool,
+        // what `QueryClient::max_queries` evicts by
+        last_used: i64,
+        // what `QueryClient::reset_queries` goes back to
+        initial_data: Option<(InitialData<TData>, Option<i64>)>,
+    }
+
+    impl<TData> Query<TData>
+    where
+        TData: Clone + 'static,
+    {
+        // fetches into the cached query. it's only borrowed for the state
+        // updates, so observers can read it while the fetch is pending
+        pub async fn fetch(query: Rc<RefCell<Self>>) {
+            Fetch::new(query, None).run().await
+        }
+
+        // fetches with `priority` instead of the query's own
+        pub(crate) async fn fetch_with_priority(
+            query: Rc<RefCell<Self>>,
+            priority: Option<FetchPriority>,
+        ) {
+            Fetch::new(query, priority).run().await
+        }
+
+        // fetches with `query_fn` instead of the query's own, e.g. the next
+        // page of an infinite query. it's deduplicated with the query's
+        // other fetches, cancelled with them and waits in the fetch queue.
+        // with `keep_data_on_error` a failure (after its retries) keeps the
+        // cached data, it's still reported like any failed fetch
+        pub(crate) async fn fetch_with(
+            query: Rc<RefCell<Self>>,
+            query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
+            keep_data_on_error: bool,
+        ) {
+            let fetch = Fetch {
+                query_fn,
+                intercept: false,
+                keep_data_on_error,
+                ..Fetch::new(query, None)
+            };
+            fetch.run().await
+        }
+
+        // a fetch that doesn't borrow the query until it's polled
+        fn shared_fetch(&self) -> Pin<Box<dyn Future<Output = ()>>> {
+            match self.this.0.upgrade() {
+                Some(query) => Box::pin(Query::fetch(query)),
+                // not cached, so nobody would see the result
+                None => Box::pin(async {}),
+            }
+        }
+
+        // what any of its subscribers refetch on
+        pub fn refetch_triggers(&self) -> RefetchTriggers {
+            self.subscribers
+                .iter()
+                .fold(RefetchTriggers::NONE, |triggers, subscriber| {
+                    triggers.union(subscriber.refetch_triggers)
+                })
+        }
+
+        fn is_unchanged(&self, data: &TData) -> bool {
+            match (&self.is_equal, &self.state.status) {
+                (Some(is_equal), Status::Success(old)) => (is_equal.0)(old, data),
+                _ => false,
+            }
+        }
+
+        // stale once the shortest `stale_time` among its subscribers has
+        // elapsed, inactive queries are always stale
+        pub fn is_stale(&self) -> bool {
+            self.state.is_stale_after(self.stale_time())
+        }
+
+        fn stale_time(&self) -> Option<i64> {
+            self.subscribers
+                .iter()
+                .map(|subscriber| subscriber.stale_time)
+                .min()
+        }
+
+        pub(crate) fn set_state(
+            &mut self,
+            updater: impl Fn(QueryState<TData>) -> QueryState<TData>,
+        ) {
+            let mut state = updater(self.state.clone());
+            state.is_stale = state.is_stale_after(self.stale_time());
+            // nothing for the subscribers to rerender
+            if state.is_same(&self.state) {
+                return;
+            }
+            self.state = state;
+            self.purge_dropped_subscribers();
+            for subscriber in &self.subscribers {
+                self.client.notify_manager.schedule(&subscriber.callback);
+            }
+            self.client.emit_event(QueryEvent::QueryUpdated {
+                query_key: self.query_key.clone(),
+            });
+            self.client.notify();
+        }
+
+        // spawns a fetch unless the data is younger than `stale_time`
+        fn fetch_if_older_than(&self, stale_time: i64) {
+            if self.state.is_stale_after(Some(stale_time)) {
+                wasm_bindgen_futures::spawn_local(self.shared_fetch());
+            }
+        }
+
+        fn subscribe(&mut self, subscriber: QuerySubscriber) {
+            self.subscribers.push(subscriber);
+            self.last_used = now();
+            self.unschedule_query_cleanup();
+            self.client.emit_event(QueryEvent::ObserverAdded {
+                query_key: self.query_key.clone(),
+            });
+        }
+
+        fn unsubscribe(&mut self, callback: Callback<()>) {
+            self.subscribers
+                .retain(|subscriber| subscriber.callback != callback);
+            self.last_used = now();
+            self.client.emit_event(QueryEvent::ObserverRemoved {
+                query_key: self.query_key.clone(),
+            });
+
+            if self.subscribers.is_empty() {
+                self.on_last_unsubscribe();
+            }
+        }
+
+        // whether invalidating or resetting it should refetch
+        fn has_enabled_subscribers(&self) -> bool {
+            self.subscribers
+                .iter()
+                .any(|subscriber| subscriber.enabled && subscriber.is_alive())
+        }
+
+        // drops the observers that went away without unsubscribing, their
+        // callbacks would otherwise keep the component state alive
+        fn purge_dropped_subscribers(&mut self) {
+            if self.subscribers.iter().all(QuerySubscriber::is_alive) {
+                return;
+            }
+            self.subscribers.retain(QuerySubscriber::is_alive);
+            if self.subscribers.is_empty() {
+                self.on_last_unsubscribe();
+            }
+        }
+
+        fn on_last_unsubscribe(&mut self) {
+            if let Some(live) = &self.live {
+                live.stop();
+            }
+            self.schedule_query_cleanup();
+        }
+
+        pub(crate) fn schedule_query_cleanup(&mut self) {
+            // the cache may hold a new query for the key by now
+            if self.removed {
+                return;
+            }
+            self.unschedule_query_cleanup();
+            let Some(cache_time) = self.cache_time else {
+                return;
+            };
+            let query_key = self.query_key.clone();
+            let queries = self.client.queries.clone();
+            let client = self.client.clone();
+            let timeout = Rc::clone(&self.timeout);
+
+            let gc = wasm_bindgen::closure::Closure::once_into_js(move || {
+                timeout.set(None);
+                // an observer may have subscribed in the same tick
+                let query = {
+                    let mut queries = queries.borrow_mut();
+                    match queries.get(&query_key) {
+                        Some(query) if query.subscriber_count() == 0 => queries.remove(&query_key),
+                        _ => None,
+                    }
+                };
+                let query = match query {
+                    Some(query) => query,
+                    None => return,
+                };
+                // drops the cached data, observers still holding the query
+                // start over with a new one
+                query.detach();
+
+                for plugin in client.plugins.iter() {
+                    plugin.on_gc(&query_key);
+                    plugin.on_query_removed(&query_key);
+                }
+                client.emit_event(QueryEvent::Gc {
+                    query_key: query_key.clone(),
+                });
+                client.emit_event(QueryEvent::QueryRemoved { query_key });
+                client.notify()
+            });
+
+            let handle = web_sys::window()
+                .expect("Couldn't access `window`")
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    gc.unchecked_ref(),
+                    cache_time,
+                )
+                .expect("`setTimeout` didn't register");
+
+            self.timeout.set(Some(handle));
+        }
+
+        pub(crate) fn unschedule_query_cleanup(&mut self) {
+            if let Some(timeout) = self.timeout.take() {
+                web_sys::window()
+                    .expect("Couldn't access `window`")
+                    .clear_timeout_with_handle(timeout)
+            }
+        }
+    }
+
+    // a running fetch of `query`, holding what it needs from it so the
+    // query is only borrowed to update its state
+    struct Fetch<TData>
+    where
+        TData: Clone + 'static,
+    {
+        query: Rc<RefCell<Query<TData>>>,
+        client: QueryClient,
+        query_key: String,
+        query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
+        abort: Rc<RefCell<Option<web_sys::AbortController>>>,
+        in_flight: Rc<RefCell<Option<InFlight>>>,
+        meta: Option<QueryMeta>,
+        network_mode: NetworkMode,
+        priority: FetchPriority,
+        retry: Retry,
+        retry_delay: RetryDelay,
+        fetch_timeout: Option<i32>,
+        // plugins may answer the fetch instead of `query_fn`, not for
+        // one-off functions like the next page of an infinite query
+        intercept: bool,
+        // a failure leaves cached data in place instead of turning the
+        // query into `Status::Error`, e.g. when the next page failed
+        keep_data_on_error: bool,
+    }
+
+    impl<TData> Fetch<TData>
+    where
+        TData: Clone + 'static,
+    {
+        fn new(query: Rc<RefCell<Query<TData>>>, priority: Option<FetchPriority>) -> Self {
+            let cached = query.borrow();
+            let fetch = Fetch {
+                client: cached.client.clone(),
+                query_key: cached.query_key.clone(),
+                query_fn: cached.query_fn.clone(),
+                abort: Rc::clone(&cached.abort),
+                in_flight: Rc::clone(&cached.in_flight),
+                meta: cached.meta.clone(),
+                network_mode: cached.network_mode,
+                priority: priority.unwrap_or(cached.priority),
+                retry: cached.retry.clone(),
+                retry_delay: cached.retry_delay.clone(),
+                fetch_timeout: cached.fetch_timeout,
+                intercept: true,
+                keep_data_on_error: false,
+                query: Rc::clone(&query),
+            };
+            std::mem::drop(cached);
+            fetch
+        }
+
+        fn set_state(&self, updater: impl Fn(QueryState<TData>) -> QueryState<TData>) {
+            self.query.borrow_mut().set_state(updater)
+        }
+
+        async fn run(&self) {
+            // a fetch is already running, wait for it instead of calling
+            // `query_fn` again
+            let in_flight = self.in_flight.borrow().clone();
+            if let Some(in_flight) = in_flight {
+                let _ = wasm_bindgen_futures::JsFuture::from(in_flight.done).await;
+                return;
+            }
+
+            let mut resolve = None;
+            let done = js_sys::Promise::new(&mut |res, _| resolve = Some(res));
+            let resolve = resolve.expect("`Promise` executor runs synchronously");
+            *self.in_flight.borrow_mut() = Some(InFlight { done });
+
+            // spans the whole fetch including retries
+            #[cfg(feature = "tracing")]
+            {
+                let span = tracing::info_span!("query_fetch", query_key = %self.query_key);
+                tracing::Instrument::instrument(self.run_fetch(), span).await;
+            }
+            #[cfg(not(feature = "tracing"))]
+            self.run_fetch().await;
+
+            self.in_flight.borrow_mut().take();
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+
+        async fn run_fetch(&self) {
+            debug_log!(self.client, "updating state of {:?}", self.query_key);
+
+            self.set_state(|old| QueryState {
+                status: match old.status {
+                    Status::Idle => Status::Loading,
+                    status => status,
+                },
+                fetch_status: FetchStatus::Fetching,
+                ..old
+            });
+
+            let started_at = now();
+            self.client.emit_event(QueryEvent::FetchStart {
+                query_key: self.query_key.clone(),
+            });
+            for plugin in self.client.plugins.iter() {
+                plugin.on_fetch(&self.query_key);
+            }
+
+            let intercepted = self
+                .client
+                .plugins
+                .iter()
+                .filter(|_| self.intercept)
+                .find_map(|plugin| plugin.intercept_fetch(&self.query_key, TypeId::of::<TData>()));
+            let intercepted = match intercepted {
+                Some(intercepted) => intercepted.await.and_then(|result| match result {
+                    Ok(data) => data.downcast().ok().map(|data| Ok(*data)),
+                    Err(err) => Some(Err(err)),
+                }),
+                None => None,
+            };
+            let result = match intercepted {
+                Some(result) => result,
+                None => {
+                    let controller =
+                        web_sys::AbortController::new().expect("Couldn't create `AbortController`");
+                    *self.abort.borrow_mut() = Some(controller.clone());
+                    let signal = controller.signal();
+                    let result = self.run_query_fn_with_retry(&signal).await;
+
+                    let mut abort = self.abort.borrow_mut();
+                    if abort.as_ref() == Some(&controller) {
+                        abort.take();
+                    }
+                    std::mem::drop(abort);
+
+                    // a cancelled fetch leaves the query as it was
+                    if signal.aborted() {
+                        self.client.emit_event(QueryEvent::Cancelled {
+                            query_key: self.query_key.clone(),
+                        });
+                        self.set_state(|old| QueryState {
+                            fetch_status: FetchStatus::Idle,
+                            ..old
+                        });
+                        return;
+                    }
+                    result
+                }
+            };
+
+            self.client.emit_event(QueryEvent::FetchEnd {
+                query_key: self.query_key.clone(),
+                duration: now() - started_at,
+            });
+
+            let error = result.as_ref().err().cloned();
+            match result {
+                Ok(data) => {
+                    for plugin in self.client.plugins.iter() {
+                        plugin.on_success(&self.query_key, &data as &dyn Any);
+                    }
+                    if let Some(on_query_success) = &self.client.on_query_success {
+                        on_query_success.emit(self.query_key.clone());
+                    }
+                    let mut query = self.query.borrow_mut();
+                    if query.is_unchanged(&data) {
+                        // keep the cached value, the `fetch_status` update
+                        // below still notifies subscribers
+                        query.state.last_updated = Some(now());
+                        query.state.is_invalidated = false;
+                        std::mem::drop(query);
+                    } else {
+                        std::mem::drop(query);
+                        let data = Rc::new(data);
+                        self.set_state(|old| QueryState {
+                            status: Status::Success(data.clone()),
+                            last_updated: Some(now()),
+                            is_invalidated: false,
+                            ..old
+                        });
+                    }
+                }
+                Err(err) => {
+                    self.client.emit_event(QueryEvent::Error {
+                        query_key: self.query_key.clone(),
+                        error: err.clone(),
+                    });
+                    for plugin in self.client.plugins.iter() {
+                        plugin.on_error(&self.query_key, &err);
+                    }
+                    if let Some(on_query_error) = &self.client.on_query_error {
+                        on_query_error.emit((self.query_key.clone(), err.clone()));
+                    }
+                    let has_data = matches!(self.query.borrow().state.status, Status::Success(_));
+                    if !(self.keep_data_on_error && has_data) {
+                        self.set_state(|old| QueryState {
+                            status: Status::Error(err.clone()),
+                            ..old
+                        })
+                    }
+                }
+            };
+
+            self.set_state(|old| QueryState {
+                fetch_status: FetchStatus::Idle,
+                ..old
+            });
+
+            let on_settled = self
+                .query
+                .borrow()
+                .subscribers
+                .iter()
+                .filter(|subscriber| subscriber.is_alive())
+                .filter_map(|subscriber| subscriber.on_settled.clone())
+                .collect::<Vec<_>>();
+            for on_settled in on_settled {
+                on_settled.emit(error.clone());
+            }
+
+            debug_log!(self.client, "new state for {:?}", self.query_key);
+        }
+
+        // waits for `retry_delay` between attempts
+        async fn run_query_fn_with_retry(
+            &self,
+            signal: &web_sys::AbortSignal,
+        ) -> QueryResult<TData> {
+            let mut failures = 0;
+            loop {
+                let offline_first = self.network_mode == NetworkMode::OfflineFirst;
+                if self.network_mode != NetworkMode::Always
+                    && !(offline_first && failures == 0)
+                    && !is_online()
+                {
+                    self.set_state(|old| QueryState {
+                        fetch_status: FetchStatus::Paused,
+                        ..old
+                    });
+                    until_online().await;
+                    self.set_state(|old| QueryState {
+                        fetch_status: FetchStatus::Fetching,
+                        ..old
+                    });
+                    if signal.aborted() {
+                        break Err(String::from("cancelled while paused"));
+                    }
+                }
+
+                match self.run_query_fn(signal).await {
+                    Err(err) if !signal.aborted() && self.retry.should_retry(failures, &err) => {
+                        let delay = self.retry_delay.delay(failures, &err);
+                        failures += 1;
+                        sleep(delay).await;
+                        if signal.aborted() {
+                            break Err(err);
+                        }
+                    }
+                    result => break result,
+                }
+            }
+        }
+
+        async fn run_query_fn(&self, signal: &web_sys::AbortSignal) -> QueryResult<TData> {
+            if cfg!(debug_assertions) {
+                if let Some(faults) = self.client.dev_faults {
+                    if faults.latency > 0 {
+                        sleep(faults.latency).await;
+                    }
+                    if js_sys::Math::random() < faults.failure_rate {
+                        return Err(String::from("injected failure"));
+                    }
+                }
+            }
+
+            let _slot = self.client.fetch_queue.acquire(self.priority).await;
+            // cancelled while waiting for a slot
+            if signal.aborted() {
+                return Err(String::from("cancelled"));
+            }
+            match self.fetch_timeout {
+                Some(timeout) => self.run_query_fn_with_timeout(signal, timeout).await,
+                None => self.query_fn.emit(self.context(signal.clone())).await,
+            }
+        }
+
+        // the attempt gets its own signal, aborted once `timeout` ms passed
+        // or when the fetch is cancelled. only the latter aborts `signal`,
+        // so a timed out attempt is retried
+        async fn run_query_fn_with_timeout(
+            &self,
+            signal: &web_sys::AbortSignal,
+            timeout: i32,
+        ) -> QueryResult<TData> {
+            let attempt =
+                web_sys::AbortController::new().expect("Couldn't create `AbortController`");
+            let forward = wasm_bindgen::closure::Closure::wrap(Box::new({
+                let attempt = attempt.clone();
+                move || attempt.abort()
+            }) as Box<dyn FnMut()>);
+            let _ =
+                signal.add_event_listener_with_callback("abort", forward.as_ref().unchecked_ref());
+
+            let mut query_fn = Box::pin(self.query_fn.emit(self.context(attempt.signal())));
+            let mut timer = Box::pin(sleep(timeout));
+            let result = std::future::poll_fn(|cx| match query_fn.as_mut().poll(cx) {
+                Poll::Ready(result) => Poll::Ready(Some(result)),
+                Poll::Pending => timer.as_mut().poll(cx).map(|_| None),
+            })
+            .await;
+
+            let _ = signal
+                .remove_event_listener_with_callback("abort", forward.as_ref().unchecked_ref());
+            result.unwrap_or_else(|| {
+                attempt.abort();
+                Err(format!("timed out after {}ms", timeout))
+            })
+        }
+
+        fn context(&self, signal: web_sys::AbortSignal) -> QueryFunctionContext {
+            QueryFunctionContext {
+                query_key: self.query_key.clone(),
+                signal,
+                meta: self.meta.clone(),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct QueryState<TData>
+    where
+        TData: Clone,
+    {
+        pub status: Status<TData>,
+        pub fetch_status: FetchStatus,
+        pub is_previous_data: bool,
+        pub last_updated: Option<i64>,
+        // stale regardless of `stale_time` until the next successful fetch
+        pub is_invalidated: bool,
+        // `status` holds `QueryOptions::placeholder_data`, not cached data
+        pub is_placeholder_data: bool,
+        // older than the observer's `stale_time`, or invalidated. observers
+        // rerender once their data turns stale
+        pub is_stale: bool,
+    }
+
+    impl<TData> QueryState<TData>
+    where
+        TData: Clone,
+    {
+        // the same state with `f` applied to the data
+        pub fn map<U: Clone>(&self, f: impl FnOnce(&TData) -> U) -> QueryState<U> {
+            QueryState {
+                status: match &self.status {
+                    Status::Idle => Status::Idle,
+                    Status::Loading => Status::Loading,
+                    Status::Success(data) => Status::Success(Rc::new(f(data))),
+                    Status::Error(err) => Status::Error(err.clone()),
+                },
+                fetch_status: self.fetch_status,
+                is_previous_data: self.is_previous_data,
+                last_updated: self.last_updated,
+                is_invalidated: self.is_invalidated,
+                is_placeholder_data: self.is_placeholder_data,
+                is_stale: self.is_stale,
+            }
+        }
+
+        // `None` counts as stale right away
+        pub(crate) fn is_stale_after(&self, stale_time: Option<i64>) -> bool {
+            if self.is_invalidated {
+                return true;
+            }
+            match (self.last_updated, stale_time) {
+                (Some(last_updated), Some(stale_time)) => now() - last_updated > stale_time,
+                _ => true,
+            }
+        }
+
+        pub fn data(&self) -> Option<&TData> {
+            self.status.data()
+        }
+
+        pub fn error(&self) -> Option<&String> {
+            self.status.error()
+        }
+
+        pub fn is_idle(&self) -> bool {
+            self.status.is_idle()
+        }
+
+        pub fn is_loading(&self) -> bool {
+            self.status.is_loading()
+        }
+
+        pub fn is_success(&self) -> bool {
+            self.status.is_success()
+        }
+
+        pub fn is_error(&self) -> bool {
+            self.status.is_error()
+        }
+
+        // `f` applied to the data if there is any, e.g.
+        // `state.map_data(|posts| html! { ... }).unwrap_or_default()`
+        pub fn map_data<U>(&self, f: impl FnOnce(&TData) -> U) -> Option<U> {
+            self.data().map(f)
+        }
+
+        // a background refetch if the query already has data
+        pub fn is_fetching(&self) -> bool {
+            self.fetch_status == FetchStatus::Fetching
+        }
+
+        pub fn is_paused(&self) -> bool {
+            self.fetch_status == FetchStatus::Paused
+        }
+
+        // `==` without requiring `TData: PartialEq`, the data only counts
+        // as the same if it's the same `Rc`
+        pub(crate) fn is_same(&self, other: &Self) -> bool {
+            let same_status = match (&self.status, &other.status) {
+                (Status::Success(data), Status::Success(other)) => Rc::ptr_eq(data, other),
+                (Status::Error(err), Status::Error(other)) => err == other,
+                (status, other) => status.kind() == other.kind(),
+            };
+            same_status
+                && self.fetch_status == other.fetch_status
+                && self.is_previous_data == other.is_previous_data
+                && self.last_updated == other.last_updated
+                && self.is_invalidated == other.is_invalidated
+                && self.is_placeholder_data == other.is_placeholder_data
+                && self.is_stale == other.is_stale
+        }
+    }
+
+    fn create_query<TData>(client: QueryClient, options: &QueryOptions<TData>) -> Query<TData>
+    where
+        TData: Clone + 'static,
+    {
+        Query {
+            client,
+            state: QueryState {
+                status: Status::Idle,
+                fetch_status: FetchStatus::Fetching,
+                is_previous_data: false,
+                last_updated: None,
+                is_invalidated: false,
+                is_placeholder_data: false,
+                is_stale: true,
+            },
+            query_fn: options.query_fn.clone(),
+            subscribers: vec![],
+            query_key: options.query_key.clone(),
+            cache_time: options.cache_time,
+            timeout: Rc::new(Cell::new(None)),
+            live: options.live.clone(),
+            is_equal: options.is_equal.clone(),
+            retry: options.retry.clone(),
+            retry_delay: options.retry_delay.clone(),
+            fetch_timeout: options.fetch_timeout,
+            abort: Rc::new(RefCell::new(None)),
+            in_flight: Rc::new(RefCell::new(None)),
+            this: QueryCell(Weak::new()),
+            meta: options.meta.clone(),
+            network_mode: options.network_mode,
+            priority: options.priority,
+            removed: false,
+            last_used: now(),
+            initial_data: options.initial_data.clone(),
+        }
+    }
+
+    // what a query keeps of each of its observers. holding the observer
+    // itself would be a cycle through `Subscriber::query`, so only a weak
+    // token of it is kept and dropped observers get purged on notify
+    #[derive(Clone, Debug)]
+    pub struct QuerySubscriber {
+        stale_time: i64,
+        refetch_triggers: RefetchTriggers,
+        enabled: bool,
+        callback: Callback<()>,
+        // called with the error, if any, once a fetch of the query settled.
+        // not when it's cancelled
+        on_settled: Option<Callback<Option<String>>>,
+        alive: Weak<()>,
+    }
+
+    impl QuerySubscriber {
+        pub fn is_alive(&self) -> bool {
+            self.alive.strong_count() > 0
+        }
+    }
+
+    impl PartialEq for QuerySubscriber {
+        fn eq(&self, other: &Self) -> bool {
+            self.callback == other.callback && self.alive.ptr_eq(&other.alive)
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct Subscriber<TData>
+    where
+        TData: Clone + 'static,
+    {
+        query: Rc<RefCell<Query<TData>>>,
+        stale_time: i64,
+        refetch_triggers: RefetchTriggers,
+        refetch_on_mount: RefetchOnMount,
+        // a disabled observer only reads the cache, it never fetches on its
+        // own
+        enabled: bool,
+        // shared by the clones of the observer, see `QuerySubscriber`
+        alive: Rc<()>,
+    }
+
+    impl<TData> Subscriber<TData>
+    where
+        TData: Clone + 'static,
+    {
+        // `is_stale` by this observer's `stale_time`
+        pub fn get_result(&self) -> QueryState<TData> {
+            let mut state = self.query.borrow().state.clone();
+            state.is_stale = state.is_stale_after(Some(self.stale_time));
+            state
+        }
+
+        // in ms, `i64::MAX` for `StaleTime::Infinite`
+        pub fn stale_time(&self) -> i64 {
+            self.stale_time
+        }
+
+        pub fn query_key(&self) -> String {
+            self.query.borrow().query_key.clone()
+        }
+
+        // the query was removed from the cache, a new observer is needed
+        pub fn is_removed(&self) -> bool {
+            self.query.borrow().removed
+        }
+
+        // `callback` stays subscribed until the returned `Subscription` is
+        // dropped
+        pub fn subscribe(&mut self, callback: Callback<()>) -> Subscription<TData> {
+            self.subscribe_with_settled(callback, None)
+        }
+
+        // like `subscribe`, `on_settled` is also called with the error, if
+        // any, once every fetch of the query settles while subscribed, even
+        // if nothing rerenders in between
+        pub fn subscribe_with_settled(
+            &mut self,
+            callback: Callback<()>,
+            on_settled: Option<Callback<Option<String>>>,
+        ) -> Subscription<TData> {
+            let mut x = (*self.query).borrow_mut();
+            x.subscribe(QuerySubscriber {
+                stale_time: self.stale_time,
+                refetch_triggers: self.refetch_triggers,
+                enabled: self.enabled,
+                callback: callback.clone(),
+                on_settled,
+                alive: Rc::downgrade(&self.alive),
+            });
+            let live = x.live.clone();
+            let has_data = x.state.last_updated.is_some();
+            std::mem::drop(x);
+            if self.enabled {
+                if let Some(live) = live {
+                    live.start(&self.query);
+                }
+                match (self.refetch_on_mount, has_data) {
+                    (RefetchOnMount::Always, true) => {
+                        wasm_bindgen_futures::spawn_local(Query::fetch(Rc::clone(&self.query)))
+                    }
+                    (RefetchOnMount::Never, true) => {}
+                    _ => self.fetch(),
+                }
+            }
+
+            Subscription {
+                query: Rc::clone(&self.query),
+                callback,
+            }
+        }
+
+        pub fn refetch_triggers(&self) -> RefetchTriggers {
+            self.refetch_triggers
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            self.enabled
+        }
+
+        pub fn fetch(&mut self) {
+            self.query.borrow().fetch_if_older_than(self.stale_time)
+        }
+
+        // fetch regardless of staleness and resolve with the settled state
+        pub async fn refetch(&self) -> QueryState<TData> {
+            Query::fetch(Rc::clone(&self.query)).await;
+            self.get_result()
+        }
+    }
+
+    // removes its callback from the query when dropped, starting the
+    // garbage collection countdown if it was the last one
+    #[must_use = "dropping a `Subscription` unsubscribes right away"]
+    pub struct Subscription<TData>
+    where
+        TData: Clone + 'static,
+    {
+        query: Rc<RefCell<Query<TData>>>,
+        callback: Callback<()>,
+    }
+
+    impl<TData> Drop for Subscription<TData>
+    where
+        TData: Clone + 'static,
+    {
+        fn drop(&mut self) {
+            match self.query.try_borrow_mut() {
+                Ok(mut query) => query.unsubscribe(self.callback.clone()),
+                // dropped from within one of the query's callbacks
+                Err(_) => {
+                    let query = Rc::clone(&self.query);
+                    let callback = self.callback.clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        query.borrow_mut().unsubscribe(callback)
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn create_query_observer<TData>(
+        client: &mut QueryClient,
+        options: QueryOptions<TData>,
+    ) -> Subscriber<TData>
+    where
+        TData: Clone + 'static,
+    {
+        let query = client.get_query(&options);
+        let mut refetch_triggers = options.refetch_triggers.unwrap_or(client.refetch_triggers);
+        if let Some(reconnect) = options.refetch_on_reconnect {
+            refetch_triggers.reconnect = reconnect;
+        }
+        let refetch_on_mount = options
+            .refetch_on_mount
+            .unwrap_or(if refetch_triggers.mount {
+                RefetchOnMount::IfStale
+            } else {
+                RefetchOnMount::Never
+            });
+        refetch_triggers.mount = refetch_on_mount != RefetchOnMount::Never;
+        if !options.enabled {
+            refetch_triggers = RefetchTriggers::NONE;
+        }
+        Subscriber {
+            query,
+            stale_time: options.stale_time,
+            refetch_triggers,
+            refetch_on_mount,
+            enabled: options.enabled,
+            alive: Rc::new(()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cache_stats_record_counts_per_query() {
+            let mut stats = CacheStats::default();
+            let query_key = || String::from("posts");
+            stats.record(&QueryEvent::CacheMiss {
+                query_key: query_key(),
+            });
+            stats.record(&QueryEvent::CacheHit {
+                query_key: query_key(),
+            });
+            stats.record(&QueryEvent::CacheHit {
+                query_key: String::from("todos"),
+            });
+            stats.record(&QueryEvent::FetchEnd {
+                query_key: query_key(),
+                duration: 30,
+            });
+            stats.record(&QueryEvent::FetchEnd {
+                query_key: query_key(),
+                duration: 10,
+            });
+            stats.record(&QueryEvent::Error {
+                query_key: query_key(),
+                error: String::from("boom"),
+            });
+            // not counted
+            stats.record(&QueryEvent::QueryAdded {
+                query_key: query_key(),
+            });
+
+            assert_eq!(
+                (
+                    stats.fetches,
+                    stats.cache_hits,
+                    stats.cache_misses,
+                    stats.errors
+                ),
+                (2, 2, 1, 1)
+            );
+            assert_eq!(stats.hit_rate(), Some(2.0 / 3.0));
+            let posts = &stats.queries["posts"];
+            assert_eq!(
+                (
+                    posts.fetches,
+                    posts.cache_hits,
+                    posts.cache_misses,
+                    posts.errors
+                ),
+                (2, 1, 1, 1)
+            );
+            assert_eq!(posts.average_fetch_duration(), Some(20.0));
+            assert_eq!(stats.queries["todos"].average_fetch_duration(), None);
+            assert_eq!(CacheStats::default().hit_rate(), None);
+        }
+    }
+}
+
+pub use utils::{
+    AnyQuery, CacheStats, DevFaults, FetchStatus, InitialData, IsEqual, LiveSink, LiveSubscribe,
+    LoggedEvent, NotifyOnChangeProps, Query, QueryCache, QueryClient, QueryEvent, QueryFnConflict,
+    QueryFunctionContext, QueryHandle, QueryHandleOptions, QueryInfo, QueryMeta, QueryPlugin,
+    QueryState, QueryStats, QueryStatus, QueryStatusState, QuerySubscriber, RefetchInterval,
+    RefetchIntervalFn, RefetchTriggers, Retry, RetryDelay, RetryDelayFn, RetryIf, Status,
+    Subscription,
+};
+use yew::{
+    function_component, html, use_context, use_effect_with_deps, use_mut_ref, use_state, Callback,
+    Children, ContextProvider, Properties, UseStateHandle,
+};
+
+pub struct QueryOptions<TData>
+where
+    TData: Clone + 'static,
+{
+    // fresh data isn't refetched on mount or on refetch triggers, `0` by
+    // default
+    pub stale_time: Option<StaleTime>,
+    // how long the query stays cached once it's unused, 5 minutes by
+    // default
+    pub cache_time: Option<GcTime>,
+    // keep returning the previous key's data (with `is_previous_data` set)
+    // until the query for a new key has settled, e.g. so paging doesn't
+    // flash back to `Loading`
+    pub keep_previous_data: bool,
+    // poll the query every `refetch_interval` while it's mounted, or as
+    // often as the function returns based on the latest state. each
+    // consecutive failure doubles the delay, up to `refetch_interval_max`
+    pub refetch_interval: Option<RefetchInterval<TData>>,
+    pub refetch_interval_max: Option<Duration>,
+    // keep polling while the page is hidden, by default polls are skipped
+    // until the tab is back in the foreground
+    pub refetch_interval_in_background: bool,
+    // keep the query live: called with a sink when the query becomes active,
+    // returns the teardown run once it has no subscribers left
+    pub subscribe: Option<LiveSubscribe<TData>>,
+    // compares refetched data against what's cached, an equal result keeps
+    // the cached value. without it any new data counts as a change
+    pub is_equal: Option<IsEqual<TData>>,
+    // overrides the client's `refetch_triggers` for this query
+    pub refetch_triggers: Option<RefetchTriggers>,
+    // refetch the query, if stale, when the browser comes back online.
+    // takes precedence over `refetch_triggers.reconnect`
+    pub refetch_on_reconnect: Option<bool>,
+    // what a newly mounted component does with cached data,
+    // `RefetchOnMount::IfStale` by default. takes precedence over
+    // `refetch_triggers.mount`
+    pub refetch_on_mount: Option<RefetchOnMount>,
+    // whether fetches wait for the browser to be online, `NetworkMode::Online`
+    // by default. a paused fetch is `FetchStatus::Paused`
+    pub network_mode: Option<NetworkMode>,
+    // decides which fetches run first once `max_concurrent_fetches` is
+    // reached, `FetchPriority::Normal` by default
+    pub priority: Option<FetchPriority>,
+    // failed fetches are retried before the query lands in `Status::Error`,
+    // waiting `retry_delay` in between (from 1s, doubling every attempt up
+    // to 30s, with jitter by default)
+    pub retry: Option<Retry>,
+    pub retry_delay: Option<RetryDelay>,
+    // gives up on an attempt after this long, aborting its `signal`. it
+    // fails like any other attempt, so it's retried per `retry`
+    pub timeout: Option<Duration>,
+    // the component keeps rendering a fetch as not started for this long
+    // (`Status::Idle` instead of `Loading`, `FetchStatus::Idle`), so fast
+    // fetches don't flash a spinner. once shown, the loading state stays
+    // for at least `min_loading_duration`
+    pub loading_delay: Option<Duration>,
+    pub min_loading_duration: Option<Duration>,
+    // shown as `Status::Success` (with `is_placeholder_data` set) while the
+    // query has no data yet, e.g. the partial item from a list view. it's
+    // never written to the cache
+    pub placeholder_data: Option<TData>,
+    // written to the cache if the query isn't cached yet, so the first
+    // render is already `Status::Success`. it counts as updated at
+    // `initial_data_updated_at` (now by default) for `stale_time`
+    pub initial_data: Option<InitialData<TData>>,
+    pub initial_data_updated_at: Option<i64>,
+    // passed to the query function in its `QueryFunctionContext`
+    pub meta: Option<QueryMeta>,
+    // identifies the query function for `QueryFnConflict`, e.g. when
+    // different functions are passed as the same boxed or `fn` type.
+    // without it functions are told apart by their closure type
+    pub query_fn_id: Option<String>,
+    // called once a fetch of the query settles while the component
+    // observes it, even if the component doesn't rerender in between
+    pub on_success: Option<Callback<Rc<TData>>>,
+    pub on_error: Option<Callback<String>>,
+    pub on_settled: Option<Callback<()>>,
+    // only rerender the component when these parts of the state change,
+    // e.g. leave out `fetch_status` for a component without a spinner.
+    // `use_query_select` already decides by the selected value
+    pub notify_on_change_props: Option<NotifyOnChangeProps>,
+    // a disabled query never fetches on its own, not on mount, on refetch
+    // triggers or when invalidated, e.g. while a dependent query is missing
+    // its input. it's `Status::Idle` until it has data. `refetch` still
+    // fetches. `true` by default
+    pub enabled: bool,
+}
+
+impl<TData> Default for QueryOptions<TData>
+where
+    TData: Clone + 'static,
+{
+    fn default() -> Self {
+        Self {
+            stale_time: None,
+            cache_time: None,
+            keep_previous_data: false,
+            refetch_interval: None,
+            refetch_interval_max: None,
+            refetch_interval_in_background: false,
+            subscribe: None,
+            is_equal: None,
+            refetch_triggers: None,
+            refetch_on_reconnect: None,
+            refetch_on_mount: None,
+            network_mode: None,
+            priority: None,
+            retry: None,
+            retry_delay: None,
+            timeout: None,
+            loading_delay: None,
+            min_loading_duration: None,
+            placeholder_data: None,
+            initial_data: None,
+            initial_data_updated_at: None,
+            meta: None,
+            query_fn_id: None,
+            on_success: None,
+            on_error: None,
+            on_settled: None,
+            notify_on_change_props: None,
+            enabled: true,
+        }
+    }
+}
+
+impl<TData> QueryOptions<TData>
+where
+    TData: Clone + PartialEq + 'static,
+{
+    // compares refetched data with `==` unless `is_equal` is set, so
+    // equal data keeps the cached `Rc` and doesn't rerender
+    pub fn structural_sharing(mut self) -> Self {
+        if self.is_equal.is_none() {
+            self.is_equal = Some(Rc::new(|old: &TData, new: &TData| old == new));
+        }
+        self
+    }
+}
+
+impl<TData> QueryOptions<TData>
+where
+    TData: Clone + 'static,
+{
+    // what the query is created with, with the client's defaults (and
+    // then the built-in ones) filled in
+    fn resolve(
+        &self,
+        client: &QueryClient,
+        query_key: &str,
+        query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
+    ) -> utils::QueryOptions<TData> {
+        let defaults = &client.default_options;
+
+        utils::QueryOptions {
+            query_fn: match &self.query_fn_id {
+                Some(id) => query_fn.with_id(id.clone()),
+                None => query_fn,
+            },
+            query_key: query_key.to_string(),
+            stale_time: self
+                .stale_time
+                .or(defaults.stale_time)
+                .map_or(0, StaleTime::as_millis),
+            cache_time: self
+                .cache_time
+                .or(defaults.cache_time)
+                .map_or(Some(FIX_MINUTES_MS), GcTime::as_millis),
+            live: self.subscribe.clone().map(utils::LiveQuery::new),
+            is_equal: self.is_equal.clone().map(utils::DataEq),
+            refetch_triggers: self.refetch_triggers,
+            refetch_on_reconnect: self.refetch_on_reconnect.or(defaults.refetch_on_reconnect),
+            refetch_on_mount: self.refetch_on_mount.or(defaults.refetch_on_mount),
+            network_mode: self
+                .network_mode
+                .or(defaults.network_mode)
+                .unwrap_or_default(),
+            priority: self.priority.unwrap_or_default(),
+            retry: self
+                .retry
+                .clone()
+                .or_else(|| defaults.retry.clone())
+                .unwrap_or_default(),
+            retry_delay: self
+                .retry_delay
+                .clone()
+                .or_else(|| defaults.retry_delay.clone())
+                .unwrap_or_default(),
+            fetch_timeout: self.timeout.or(defaults.timeout).map(millis),
+            initial_data: self
+                .initial_data
+                .clone()
+                .map(|initial_data| (initial_data, self.initial_data_updated_at)),
+            meta: self.meta.clone(),
+            enabled: self.enabled,
+        }
+    }
+}
+
+// the options of `QueryOptions` that don't depend on the data type, set
+// client-wide with `QueryClient::default_options`
+#[derive(Clone, Default)]
+pub struct DefaultQueryOptions {
+    pub stale_time: Option<StaleTime>,
+    pub cache_time: Option<GcTime>,
+    pub refetch_interval: Option<Duration>,
+    pub refetch_on_reconnect: Option<bool>,
+    pub refetch_on_mount: Option<RefetchOnMount>,
+    pub network_mode: Option<NetworkMode>,
+    pub retry: Option<Retry>,
+    pub retry_delay: Option<RetryDelay>,
+    pub timeout: Option<Duration>,
+}
+
+// returned by `QueryClient::builder`, collects the default query options
+// next to the client's other settings
+pub struct QueryClientBuilder {
+    client: QueryClient,
+    defaults: DefaultQueryOptions,
+}
+
+impl QueryClientBuilder {
+    pub fn stale_time(mut self, stale_time: impl Into<StaleTime>) -> Self {
+        self.defaults.stale_time = Some(stale_time.into());
+        self
+    }
+
+    pub fn cache_time(mut self, cache_time: impl Into<GcTime>) -> Self {
+        self.defaults.cache_time = Some(cache_time.into());
+        self
+    }
+
+    pub fn refetch_interval(mut self, refetch_interval: Duration) -> Self {
+        self.defaults.refetch_interval = Some(refetch_interval);
+        self
+    }
+
+    pub fn refetch_on_reconnect(mut self, refetch_on_reconnect: bool) -> Self {
+        self.defaults.refetch_on_reconnect = Some(refetch_on_reconnect);
+        self
+    }
+
+    pub fn refetch_on_mount(mut self, refetch_on_mount: RefetchOnMount) -> Self {
+        self.defaults.refetch_on_mount = Some(refetch_on_mount);
+        self
+    }
+
+    pub fn network_mode(mut self, network_mode: NetworkMode) -> Self {
+        self.defaults.network_mode = Some(network_mode);
+        self
+    }
+
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.defaults.retry = Some(retry);
+        self
+    }
+
+    pub fn retry_delay(mut self, retry_delay: impl Into<RetryDelay>) -> Self {
+        self.defaults.retry_delay = Some(retry_delay.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.defaults.timeout = Some(timeout);
+        self
+    }
+
+    pub fn refetch_triggers(mut self, triggers: RefetchTriggers) -> Self {
+        self.client = self.client.refetch_triggers(triggers);
+        self
+    }
+
+    pub fn plugin(mut self, plugin: impl QueryPlugin + 'static) -> Self {
+        self.client = self.client.with_plugin(plugin);
+        self
+    }
+
+    #[cfg(feature = "persist")]
+    pub fn persister<TData>(mut self, persister: plugins::LocalStoragePersister<TData>) -> Self
+    where
+        TData: Clone + serde::Serialize + serde::de::DeserializeOwned + 'static,
+    {
+        self.client = self.client.with_persister(persister);
+        self
+    }
+
+    pub fn on_event(mut self, callback: Callback<QueryEvent>) -> Self {
+        self.client = self.client.on_event(callback);
+        self
+    }
+
+    pub fn on_query_success(mut self, callback: Callback<String>) -> Self {
+        self.client = self.client.on_query_success(callback);
+        self
+    }
+
+    pub fn on_query_error(mut self, callback: Callback<(String, String)>) -> Self {
+        self.client = self.client.on_query_error(callback);
+        self
+    }
+
+    pub fn logger(mut self, logger: impl Logger + 'static) -> Self {
+        self.client = self.client.with_logger(logger);
+        self
+    }
+
+    pub fn max_concurrent_fetches(mut self, limit: usize) -> Self {
+        self.client = self.client.max_concurrent_fetches(limit);
+        self
+    }
+
+    pub fn max_queries(mut self, max_queries: usize) -> Self {
+        self.client = self.client.max_queries(max_queries);
+        self
+    }
+
+    pub fn build(self) -> QueryClient {
+        self.client.default_options(self.defaults)
+    }
+}
+
+impl QueryClient {
+    pub fn builder() -> QueryClientBuilder {
+        QueryClientBuilder {
+            client: QueryClient::new(),
+            defaults: DefaultQueryOptions::default(),
+        }
+    }
+}
+
+const FIX_MINUTES_MS: i32 = 5 * 60 * 1000;
+
+impl QueryClient {
+    // fetches `query_key` into the cache without subscribing to it, e.g. on
+    // link hover, unless it has data younger than `options.stale_time`.
+    // like an unmounted query it's garbage collected after `cache_time`
+    // unless something subscribes first
+    pub async fn prefetch_query<TData, F>(
+        &self,
+        query_key: &str,
+        query_fn: F,
+        options: QueryOptions<TData>,
+    ) where
+        TData: Clone + 'static,
+        F: 'static + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = QueryResult<TData>>>>,
+    {
+        // speculative, so it doesn't hold up the fetches of mounted queries
+        let options = QueryOptions {
+            priority: options.priority.or(Some(FetchPriority::Low)),
+            ..options
+        };
+        let _ = self.fetch_query(query_key, query_fn, options).await;
+    }
+
+    // `prefetch_query` resolving with the data, e.g. for event handlers and
+    // route loaders. data younger than `options.stale_time` is returned
+    // without fetching
+    pub async fn fetch_query<TData, F>(
+        &self,
+        query_key: &str,
+        query_fn: F,
+        options: QueryOptions<TData>,
+    ) -> QueryResult<TData>
+    where
+        TData: Clone + 'static,
+        F: 'static + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = QueryResult<TData>>>>,
+    {
+        let priority = options.priority;
+        let options = options.resolve(self, query_key, FnPtr::from(query_fn));
+        let query = self.clone().get_query(&options);
+
+        {
+            let query = query.borrow();
+            let is_fresh = !query.state.is_invalidated
+                && query
+                    .state
+                    .last_updated
+                    .is_some_and(|last_updated| now() - last_updated <= options.stale_time);
+            if let (true, Status::Success(data)) = (is_fresh, &query.state.status) {
+                return Ok((**data).clone());
+            }
+        }
+        Query::fetch_with_priority(Rc::clone(&query), priority).await;
+
+        let mut query = query.borrow_mut();
+        if query.subscribers.is_empty() {
+            // restarts the countdown of an earlier prefetch
+            query.schedule_query_cleanup();
+        }
+
+        match query.state.status.clone() {
+            Status::Success(data) => Ok((*data).clone()),
+            Status::Error(err) => Err(err),
+            // the fetch was cancelled before the query had data
+            Status::Idle | Status::Loading => Err(String::from("cancelled")),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum MutationStatus<TData, TError> {
+    Idle,
+    Loading,
+    // waiting for the browser to come back online
+    Paused,
+    Success(TData),
+    Error(TError),
+}
+
+// `MutationStatus` without the data or error
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MutationStatusKind {
+    Idle,
+    Loading,
+    Paused,
+    Success,
+    Error,
+}
+
+impl<TData, TError> MutationStatus<TData, TError> {
+    pub fn kind(&self) -> MutationStatusKind {
+        match self {
+            MutationStatus::Idle => MutationStatusKind::Idle,
+            MutationStatus::Loading => MutationStatusKind::Loading,
+            MutationStatus::Paused => MutationStatusKind::Paused,
+            MutationStatus::Success(_) => MutationStatusKind::Success,
+            MutationStatus::Error(_) => MutationStatusKind::Error,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MutationState<TData, TError> {
+    pub status: MutationStatus<TData, TError>,
+}
+
+// a mutation as tracked by the client's `MutationCache`. it's type-erased,
+// every `use_mutation` can have its own variables, data and error types
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mutation {
+    pub id: u32,
+    pub mutation_key: Option<String>,
+    pub status: MutationStatusKind,
+    pub submitted_at: i64,
+    pub settled_at: Option<i64>,
+    pub variables: MutationVariables,
+}
+
+// what a `Mutation` was called with, downcast it to the `TVariables` of its
+// `use_mutation`
+#[derive(Clone)]
+pub struct MutationVariables(Rc<dyn Any>);
+
+impl MutationVariables {
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl PartialEq for MutationVariables {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for MutationVariables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MutationVariables")
+    }
+}
+
+impl Mutation {
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self.status,
+            MutationStatusKind::Success | MutationStatusKind::Error
+        )
+    }
+}
+
+// every mutation run through `use_mutation` under a `QueryClientProvider`.
+// settled mutations are removed after `gc_time` ms, or never if it's
+// `None`
+#[derive(Clone)]
+pub struct MutationCache {
+    mutations: Rc<RefCell<Vec<Mutation>>>,
+    subscribers: Rc<RefCell<Vec<Callback<Mutation>>>>,
+    removed_subscribers: Rc<RefCell<Vec<Callback<u32>>>>,
+    next_id: Rc<Cell<u32>>,
+    gc_time: Option<i32>,
+}
+
+impl PartialEq for MutationCache {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.mutations, &other.mutations)
+    }
+}
+
+impl Debug for MutationCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MutationCache")
+            .field("mutations", &self.mutations.borrow().len())
+            .field("gc_time", &self.gc_time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MutationCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MutationCache {
+    pub fn new() -> Self {
+        Self {
+            mutations: Rc::new(RefCell::new(vec![])),
+            subscribers: Rc::new(RefCell::new(vec![])),
+            removed_subscribers: Rc::new(RefCell::new(vec![])),
+            next_id: Rc::new(Cell::new(0)),
+            gc_time: Some(FIX_MINUTES_MS),
+        }
+    }
+
+    pub fn mutations(&self) -> Vec<Mutation> {
+        self.mutations.borrow().clone()
+    }
+
+    // how many mutations are running or paused
+    pub fn is_mutating(&self) -> usize {
+        self.mutations
+            .borrow()
+            .iter()
+            .filter(|mutation| !mutation.is_settled())
+            .count()
+    }
+
+    // called with the mutation on every status change
+    pub fn subscribe(&self, callback: Callback<Mutation>) {
+        self.subscribers.borrow_mut().push(callback);
+    }
+
+    pub fn unsubscribe(&self, callback: Callback<Mutation>) {
+        self.subscribers
+            .borrow_mut()
+            .retain(|subscriber| subscriber.clone() != callback);
+    }
+
+    // called with the id of every mutation that's garbage collected
+    pub fn subscribe_removed(&self, callback: Callback<u32>) {
+        self.removed_subscribers.borrow_mut().push(callback);
+    }
+
+    pub fn unsubscribe_removed(&self, callback: Callback<u32>) {
+        self.removed_subscribers
+            .borrow_mut()
+            .retain(|subscriber| subscriber.clone() != callback);
+    }
+
+    fn add(&self, mutation_key: Option<String>, variables: MutationVariables) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+
+        let mutation = Mutation {
+            id,
+            mutation_key,
+            status: MutationStatusKind::Loading,
+            submitted_at: now(),
+            settled_at: None,
+            variables,
+        };
+        self.mutations.borrow_mut().push(mutation.clone());
+        self.notify(mutation);
+
+        id
+    }
+
+    fn update(&self, id: u32, status: MutationStatusKind) {
+        let mutation = {
+            let mut mutations = self.mutations.borrow_mut();
+            let Some(mutation) = mutations.iter_mut().find(|mutation| mutation.id == id) else {
+                return;
+            };
+            mutation.status = status;
+            if mutation.is_settled() {
+                mutation.settled_at = Some(now());
+            }
+            mutation.clone()
+        };
+
+        if mutation.is_settled() {
+            self.schedule_gc(id);
+        }
+        self.notify(mutation);
+    }
+
+    fn schedule_gc(&self, id: u32) {
+        let Some(gc_time) = self.gc_time else {
+            return;
+        };
+        let cache = self.clone();
+        let gc = wasm_bindgen::closure::Closure::once_into_js(move || cache.remove(id));
+
+        web_sys::window()
+            .expect("Couldn't access `window`")
+            .set_timeout_with_callback_and_timeout_and_arguments_0(gc.unchecked_ref(), gc_time)
+            .expect("`setTimeout` didn't register");
+    }
+
+    fn remove(&self, id: u32) {
+        let removed = {
+            let mut mutations = self.mutations.borrow_mut();
+            let count = mutations.len();
+            mutations.retain(|mutation| mutation.id != id);
+            mutations.len() != count
+        };
+        if !removed {
+            return;
+        }
+        let subscribers = self.removed_subscribers.borrow().clone();
+        for subscriber in subscribers {
+            subscriber.emit(id);
+        }
+    }
+
+    fn notify(&self, mutation: Mutation) {
+        let subscribers = self.subscribers.borrow().clone();
+        for subscriber in subscribers {
+            subscriber.emit(mutation.clone());
+        }
+    }
+}
+
+// what `on_mutate` hands to `on_error` and `on_settled`, e.g. the cached
+// data to roll an optimistic update back to
+#[derive(Clone)]
+pub struct MutationContext(Rc<dyn Any>);
+
+impl MutationContext {
+    pub fn new(context: impl Any) -> Self {
+        Self(Rc::new(context))
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl PartialEq for MutationContext {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for MutationContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MutationContext")
+    }
+}
+
+pub struct MutationOptions<TData, TError, TVariables = ()> {
+    // runs before the mutation function, e.g. to cancel the affected
+    // queries and write the expected result to the cache. the context it
+    // returns is passed to `on_error` (to roll back) and `on_settled`
+    pub on_mutate: Option<FnPtr<TVariables, Option<MutationContext>>>,
+    pub on_success: Option<FnPtr<Rc<TData>, ()>>,
+    pub on_settled: Option<FnPtr<Option<MutationContext>, ()>>,
+    pub on_error: Option<FnPtr<(TError, Option<MutationContext>), ()>>,
+    // failed attempts are retried per `retry` (never by default), waiting
+    // `retry_delay` in between (from 1s, doubling every attempt up to 30s,
+    // with jitter by default), like failed fetches
+    pub retry: Retry<TError>,
+    pub retry_delay: RetryDelay<TError>,
+    pub network_mode: NetworkMode,
+    // identifies the mutation in the `MutationCache`
+    pub mutation_key: Option<String>,
+}
+
+impl<TData, TError, TVariables> Clone for MutationOptions<TData, TError, TVariables> {
+    fn clone(&self) -> Self {
+        Self {
+            on_mutate: self.on_mutate.clone(),
+            on_success: self.on_success.clone(),
+            on_settled: self.on_settled.clone(),
+            on_error: self.on_error.clone(),
+            retry: self.retry.clone(),
+            retry_delay: self.retry_delay.clone(),
+            network_mode: self.network_mode,
+            mutation_key: self.mutation_key.clone(),
+        }
+    }
+}
+
+impl<TData, TError, TVariables> Default for MutationOptions<TData, TError, TVariables> {
+    fn default() -> Self {
+        Self {
+            on_mutate: None,
+            on_success: None,
+            on_settled: None,
+            on_error: None,
+            retry: Retry::default(),
+            retry_delay: RetryDelay::default(),
+            network_mode: NetworkMode::default(),
+            mutation_key: None,
+        }
+    }
+}
+
+// registered on the client with `set_mutation_defaults` so any component
+// can run the mutation by key
+pub struct MutationDefaults<TVariables, TData, TError> {
+    pub mutation_fn: FnPtr<TVariables, Result<TData, TError>>,
+    pub options: MutationOptions<TData, TError, TVariables>,
+    // queries invalidated once the mutation succeeds
+    pub invalidates: Vec<String>,
+}
+
+impl<TVariables, TData, TError> MutationDefaults<TVariables, TData, TError>
+where
+    TData: 'static,
+{
+    // the options a mutation run by `mutation_key` uses
+    fn resolve(
+        &self,
+        client: &QueryClient,
+        mutation_key: &str,
+    ) -> MutationOptions<TData, TError, TVariables> {
+        let mut options = self.options.clone();
+        options
+            .mutation_key
+            .get_or_insert_with(|| mutation_key.to_string());
+        if !self.invalidates.is_empty() {
+            let on_success = options.on_success.take();
+            let invalidates = Rc::new(self.invalidates.clone());
+            let client = client.clone();
+
+            options.on_success = Some(FnPtr::from(move |data: Rc<TData>| {
+                let on_success = on_success.clone();
+                let invalidates = invalidates.clone();
+                let client = client.clone();
+
+                Box::pin(async move {
+                    if let Some(on_success) = on_success {
+                        on_success.emit(data).await;
+                    }
+                    for query_key in invalidates.iter() {
+                        client.invalidate_queries(query_key).await;
+                    }
+                }) as Pin<Box<dyn Future<Output = ()>>>
+            }));
+        }
+        options
+    }
+}
+
+pub struct UseMutationHandle<TVariables, TData, TError> {
+    execute: FnPtr<TVariables, Result<TData, TError>>,
+    state: UseStateHandle<MutationState<TData, TError>>,
+}
+
+impl<TVariables, TData, TError> UseMutationHandle<TVariables, TData, TError>
+where
+    TVariables: Clone + 'static,
+    TData: 'static,
+    TError: 'static,
+{
+    pub fn mutate(&self, variables: TVariables) {
+        let mutation = self.mutate_async(variables);
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = mutation.await;
+        });
+    }
+
+    // like `mutate`, but resolves with the mutation's result, e.g. to
+    // navigate only once a save has succeeded
+    pub fn mutate_async(
+        &self,
+        variables: TVariables,
+    ) -> impl Future<Output = Result<TData, TError>> {
+        let execute = self.execute.clone();
+        async move { execute.emit(variables).await }
+    }
+
+    // clears the last result back to `Idle`, e.g. to dismiss an error
+    pub fn reset(&self) {
+        self.state.set(MutationState {
+            status: MutationStatus::Idle,
+        });
+    }
+}
+
+impl<TVariables, TData, TError> Clone for UseMutationHandle<TVariables, TData, TError> {
+    fn clone(&self) -> Self {
+        Self {
+            execute: self.execute.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<TVariables, TData, TError> Deref for UseMutationHandle<TVariables, TData, TError> {
+    type Target = MutationState<TData, TError>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+// runs a single mutation, tracking it in `mutation_cache` and reporting
+// every status change to `on_status`
+pub(crate) fn run_mutation<TVariables, TData, TError>(
+    mutation_fn: FnPtr<TVariables, Result<TData, TError>>,
+    options: Rc<MutationOptions<TData, TError, TVariables>>,
+    mutation_cache: Option<MutationCache>,
+    variables: TVariables,
+    on_status: impl Fn(MutationStatus<TData, TError>) + 'static,
+) -> Pin<Box<dyn Future<Output = Result<TData, TError>>>>
+where
+    TVariables: Clone + 'static,
+    TData: Clone + 'static,
+    TError: Clone + 'static,
+{
+    let id = mutation_cache.as_ref().map(|cache| {
+        cache.add(
+            options.mutation_key.clone(),
+            MutationVariables(Rc::new(variables.clone())),
+        )
+    });
+    let set_state = move |status: MutationStatus<TData, TError>| {
+        if let (Some(cache), Some(id)) = (&mutation_cache, id) {
+            cache.update(id, status.kind());
+        }
+        on_status(status);
+    };
+
+    set_state(MutationStatus::Loading);
+
+    Box::pin(async move {
+        let context = match &options.on_mutate {
+            Some(on_mutate) => on_mutate.emit(variables.clone()).await,
+            None => None,
+        };
+
+        let mut failures = 0;
+        let result = loop {
+            let offline_first = options.network_mode == NetworkMode::OfflineFirst;
+            if options.network_mode != NetworkMode::Always
+                && !(offline_first && failures == 0)
+                && !is_online()
+            {
+                set_state(MutationStatus::Paused);
+                until_online().await;
+                set_state(MutationStatus::Loading);
+            }
+
+            match mutation_fn.emit(variables.clone()).await {
+                Err(err) if options.retry.should_retry(failures, &err) => {
+                    let delay = options.retry_delay.delay(failures, &err);
+                    failures += 1;
+                    sleep(delay).await;
+                }
+                result => break result,
+            }
+        };
+
+        macro_rules! call {
+            ($func:ident, $value:expr) => {
+                if let Some($func) = &options.$func {
+                    $func.emit($value).await
+                }
+            };
+        }
+
+        match &result {
+            Ok(data) => {
+                call!(on_success, Rc::new(data.clone()))
+            }
+            Err(err) => {
+                call!(on_error, (err.clone(), context.clone()));
+            }
+        };
+
+        call!(on_settled, context);
+
+        set_state(match &result {
+            Ok(data) => MutationStatus::Success(data.clone()),
+            Err(err) => MutationStatus::Error(err.clone()),
+        });
+
+        result
+    })
+}
+
+// change the API to builder pattern maybe?
+pub fn use_mutation<TVariables, TData, TError, F>(
+    mutation_fn: F,
+    options: MutationOptions<TData, TError, TVariables>,
+) -> UseMutationHandle<TVariables, TData, TError>
+where
+    TVariables: Clone + 'static,
+    TData: Clone + 'static,
+    TError: Clone + 'static,
+    F: 'static + Fn(TVariables) -> Pin<Box<dyn Future<Output = Result<TData, TError>>>>,
+{
+    let state = use_state(|| MutationState {
+        status: MutationStatus::Idle,
+    });
+    let mutation_cache = use_context::<MutationCache>();
+    let ptr = FnPtr::from(mutation_fn);
+    let options = Rc::new(options);
+    let execute = {
+        let state = state.clone();
+
+        FnPtr::from(move |variables: TVariables| {
+            let state = state.clone();
+            run_mutation(
+                ptr.clone(),
+                options.clone(),
+                mutation_cache.clone(),
+                variables,
+                move |status| state.set(MutationState { status }),
+            )
+        })
+    };
+
+    UseMutationHandle { execute, state }
+}
+
+// the mutation registered with `QueryClient::set_mutation_defaults`, an
+// `Err` if there are no defaults for `mutation_key` with these types. the
+// hooks run either way, so defaults registered later are picked up on the
+// next render
+pub fn use_mutation_key<TVariables, TData, TError>(
+    mutation_key: &str,
+) -> Result<UseMutationHandle<TVariables, TData, TError>, String>
+where
+    TVariables: Clone + 'static,
+    TData: Clone + 'static,
+    TError: Clone + 'static,
+{
+    let client = use_query_client();
+    let defaults = client.get_mutation_defaults::<TVariables, TData, TError>(mutation_key);
+
+    let options = defaults
+        .as_ref()
+        .map(|defaults| defaults.resolve(&client, mutation_key))
+        .unwrap_or_default();
+    let mutation_fn = defaults
+        .as_ref()
+        .map(|defaults| defaults.mutation_fn.clone());
+    let mutation = use_mutation(
+        move |variables| {
+            let mutation_fn = mutation_fn.clone();
+            Box::pin(async move {
+                match mutation_fn {
+                    Some(mutation_fn) => mutation_fn.emit(variables).await,
+                    // the handle isn't handed out without defaults
+                    None => std::future::pending().await,
+                }
+            })
+        },
+        options,
+    );
+
+    match defaults {
+        Some(_) => Ok(mutation),
+        None => Err(format!(
+            "No mutation defaults registered for {:?}",
+            mutation_key
+        )),
+    }
+}
+
+// `query_fn` gets a `QueryFunctionContext` with the key and `AbortSignal` of
+// the fetch. passing the signal on to the request makes
+// `QueryClient::cancel_queries` abort it
+pub fn use_query<TData, F>(
+    query_key: &str,
+    query_fn: F,
+    options: QueryOptions<TData>,
+) -> utils::QueryState<TData>
+where
+    TData: Clone + 'static,
+    F: 'static + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
+{
+    use_query_observer(query_key, query_fn, options, None)
+}
+
+// decides whether a state change re-renders the observing component
+type IsChanged<TData> = Rc<dyn Fn(&QueryState<TData>) -> bool>;
+
+fn use_query_observer<TData, F>(
+    query_key: &str,
+    query_fn: F,
+    options: QueryOptions<TData>,
+    is_changed: Option<IsChanged<TData>>,
+) -> utils::QueryState<TData>
+where
+    TData: Clone + 'static,
+    F: 'static + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
+{
+    let query_fn = FnPtr::from(query_fn);
+    let mut client = use_query_client();
+
+    let rerender = {
+        let c = use_state(|| 0);
+        move || {
+            c.set(*c + 1);
+        }
+    };
+    let create_observer = {
+        let options = options.resolve(&client, query_key, query_fn);
+
+        move |client: &mut QueryClient| {
+            debug_log!(client, "created query observer");
+
+            utils::create_query_observer(client, options)
+        }
+    };
+    // read on every poll, so a new closure doesn't restart polling
+    let refetch_interval = use_mut_ref(|| Option::<RefetchInterval<TData>>::None);
+    *refetch_interval.borrow_mut() = options.refetch_interval.clone().or_else(|| {
+        client
+            .default_options
+            .refetch_interval
+            .map(RefetchInterval::Every)
+    });
+    let polls = refetch_interval.borrow().is_some();
+    // read when a fetch settles, so the latest callbacks run
+    let callbacks = use_mut_ref(|| (None, None, None));
+    *callbacks.borrow_mut() = (
+        options.on_success.clone(),
+        options.on_error.clone(),
+        options.on_settled.clone(),
+    );
+    let refetch_interval_max = options.refetch_interval_max.map_or(FIX_MINUTES_MS, millis);
+    let in_background = options.refetch_interval_in_background;
+    let previous_result = use_mut_ref(|| Option::<QueryState<TData>>::None);
+    let observer_ref = use_mut_ref(|| Option::<utils::Subscriber<TData>>::None);
+    // bumped for every new observer, so the effect subscribes to it
+    let generation = use_mut_ref(|| 0_u32);
+    // what the last render got from the observer
+    let rendered = use_mut_ref(|| Option::<QueryState<TData>>::None);
+    let is_changed = match (is_changed, options.notify_on_change_props) {
+        (None, Some(props)) => {
+            let rendered = rendered.clone();
+            Some(Rc::new(move |state: &QueryState<TData>| {
+                rendered
+                    .borrow()
+                    .as_ref()
+                    .is_none_or(|rendered| props.changed(rendered, state))
+            }) as IsChanged<TData>)
+        }
+        (is_changed, _) => is_changed,
+    };
+
+    if observer_ref.borrow().as_ref().is_none_or(|observer| {
+        observer.query_key() != query_key
+            || observer.is_removed()
+            || observer.is_enabled() != options.enabled
+    }) {
+        *generation.borrow_mut() += 1;
+        let previous = observer_ref
+            .replace(Some(create_observer(&mut client)))
+            .map(|observer| observer.get_result())
+            .filter(|result| matches!(result.status, Status::Success(_)));
+
+        if !options.keep_previous_data {
+            previous_result.borrow_mut().take();
+        } else if previous.is_some() {
+            // if the previous key never settled, keep showing what was
+            // shown before it
+            *previous_result.borrow_mut() = previous;
+        }
+    }
+
+    // for the rerender once the data turns stale
+    let on_stale = {
+        let is_changed = is_changed.clone();
+        let rerender = rerender.clone();
+        move |observer: utils::Subscriber<TData>| {
+            if is_changed.is_none_or(|is_changed| is_changed(&observer.get_result())) {
+                rerender()
+            }
+        }
+    };
+
+    {
+        let observer_ref = observer_ref.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                let mut observer = observer_ref.borrow().clone().unwrap();
+                let cb = match is_changed {
+                    Some(is_changed) => {
+                        let observer = observer.clone();
+                        Callback::<()>::from(move |_| {
+                            let observer = observer.clone();
+                            let is_changed = is_changed.clone();
+                            let rerender = rerender.clone();
+
+                            // the notifying query is still borrowed at this point
+                            wasm_bindgen_futures::spawn_local(async move {
+                                if is_changed(&observer.get_result()) {
+                                    rerender()
+                                }
+                            });
+                        })
+                    }
+                    None => Callback::<()>::from(move |_| rerender()),
+                };
+                let on_settled = {
+                    let observer = observer.clone();
+                    // a failed fetch may have kept the data, so the error
+                    // decides rather than the status
+                    Callback::from(move |error: Option<String>| {
+                        let (on_success, on_error, on_settled) = callbacks.borrow().clone();
+                        match (error, observer.get_result().status) {
+                            (Some(err), _) => {
+                                if let Some(on_error) = on_error {
+                                    on_error.emit(err)
+                                }
+                            }
+                            (None, Status::Success(data)) => {
+                                if let Some(on_success) = on_success {
+                                    on_success.emit(data)
+                                }
+                            }
+                            _ => {}
+                        }
+                        if let Some(on_settled) = on_settled {
+                            on_settled.emit(());
+                        }
+                    })
+                };
+                let subscription = observer.subscribe_with_settled(cb, Some(on_settled));
+
+                let polling = Rc::new(Cell::new(true));
+                if polls && observer.refetch_triggers().interval {
+                    let observer = observer.clone();
+                    let polling = polling.clone();
+
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let mut failures = 0;
+                        while polling.get() {
+                            let interval = refetch_interval
+                                .borrow()
+                                .as_ref()
+                                .and_then(|interval| interval.next(&observer.get_result()));
+                            let Some(interval) = interval else {
+                                break;
+                            };
+                            let max = refetch_interval_max.max(interval);
+                            let delay =
+                                (0..failures).fold(interval, |delay, _| (delay * 2).min(max));
+                            sleep(delay).await;
+                            if !polling.get() {
+                                break;
+                            }
+                            if !in_background && !is_visible() {
+                                continue;
+                            }
+
+                            match observer.refetch().await.status {
+                                Status::Error(_) => failures += 1,
+                                _ => failures = 0,
+                            }
+                        }
+                    });
+                }
+
+                move || {
+                    polling.set(false);
+                    std::mem::drop(subscription)
+                }
+            },
+            (
+                String::from(query_key),
+                polls,
+                in_background,
+                *generation.borrow(),
+            ),
+        );
+    }
+
+    let result = observer_ref.borrow().as_ref().unwrap().get_result();
+    *rendered.borrow_mut() = Some(result.clone());
+
+    {
+        let observer = observer_ref.borrow().clone().unwrap();
+
+        use_effect_with_deps(
+            move |&(last_updated, is_stale, _)| {
+                let mounted = Rc::new(Cell::new(true));
+                // the query doesn't notify when time passes, so wait for it
+                let stale_in = last_updated
+                    .filter(|_| !is_stale)
+                    .map(|last_updated| {
+                        last_updated.saturating_add(observer.stale_time()) - now() + 1
+                    })
+                    .and_then(|ms| i32::try_from(ms.max(0)).ok());
+                if let Some(stale_in) = stale_in {
+                    let mounted = mounted.clone();
+
+                    wasm_bindgen_futures::spawn_local(async move {
+                        sleep(stale_in).await;
+                        if mounted.get() {
+                            on_stale(observer)
+                        }
+                    });
+                }
+
+                move || mounted.set(false)
+            },
+            (result.last_updated, result.is_stale, *generation.borrow()),
+        );
+    }
+
+    let (loading_delay, min_loading_duration) =
+        (options.loading_delay, options.min_loading_duration);
+    let result = match &result.status {
+        Status::Idle | Status::Loading => {
+            match (&*previous_result.borrow(), options.placeholder_data) {
+                (Some(previous), _) => QueryState {
+                    fetch_status: result.fetch_status,
+                    is_previous_data: true,
+                    ..previous.clone()
+                },
+                (None, Some(placeholder)) => QueryState {
+                    status: Status::Success(Rc::new(placeholder)),
+                    is_placeholder_data: true,
+                    ..result
+                },
+                (None, None) => result,
+            }
+        }
+        _ => {
+            previous_result.borrow_mut().take();
+            result
+        }
+    };
+
+    use_loading_delay(result, loading_delay, min_loading_duration)
+}
+
+// holds back `Loading` and the `fetch_status` of a fetch for `delay`, and
+// once they're shown keeps them for at least `min_duration`, so fetches
+// settling quickly don't flash a spinner
+fn use_loading_delay<TData>(
+    state: QueryState<TData>,
+    delay: Option<Duration>,
+    min_duration: Option<Duration>,
+) -> QueryState<TData>
+where
+    TData: Clone + 'static,
+{
+    let rerender = {
+        let c = use_state(|| 0);
+        move || {
+            c.set(*c + 1);
+        }
+    };
+    // when the running fetch was first rendered
+    let started = use_mut_ref(|| Option::<i64>::None);
+    // when its loading state was shown, and whether that was `Loading`
+    let shown = use_mut_ref(|| Option::<(i64, bool)>::None);
+
+    let at = now();
+    let fetching = state.fetch_status != FetchStatus::Idle;
+    let loading = matches!(state.status, Status::Idle | Status::Loading);
+    let delay = delay.map_or(0, |delay| i64::from(millis(delay)));
+    let min_duration = min_duration.map_or(0, |min_duration| i64::from(millis(min_duration)));
+    let mut wake_at = None;
+
+    let mut started = started.borrow_mut();
+    let mut shown = shown.borrow_mut();
+    match (fetching, *started) {
+        (true, None) => *started = Some(at),
+        (false, _) => *started = None,
+        _ => {}
+    }
+    if let (Some(since), None) = (*started, *shown) {
+        if at - since >= delay {
+            *shown = Some((at, loading));
+        } else {
+            wake_at = Some(since + delay);
+        }
+    }
+
+    let state = match *shown {
+        Some((shown_at, was_loading)) if !fetching && at - shown_at < min_duration => {
+            wake_at = Some(shown_at + min_duration);
+            QueryState {
+                status: if was_loading {
+                    Status::Loading
+                } else {
+                    state.status.clone()
+                },
+                fetch_status: FetchStatus::Fetching,
+                ..state
+            }
+        }
+        Some(_) if !fetching => {
+            *shown = None;
+            state
+        }
+        None if fetching => QueryState {
+            status: if loading {
+                Status::Idle
+            } else {
+                state.status.clone()
+            },
+            fetch_status: FetchStatus::Idle,
+            ..state
+        },
+        _ => state,
+    };
+
+    use_effect_with_deps(
+        move |&wake_at| {
+            let mounted = Rc::new(Cell::new(true));
+            if let Some(wake_at) = wake_at {
+                let mounted = mounted.clone();
+
+                wasm_bindgen_futures::spawn_local(async move {
+                    sleep(i32::try_from(wake_at - now()).unwrap_or(0).max(0)).await;
+                    if mounted.get() {
+                        rerender()
+                    }
+                });
+            }
+
+            move || mounted.set(false)
+        },
+        wake_at,
+    );
+
+    state
+}
+
+// like `use_query`, but the component gets `select` applied to the cached
+// data and only re-renders when the selected value (or the status) changes,
+// e.g. to subscribe to just the titles of a list
+pub fn use_query_select<TData, TSelected, F, S>(
+    query_key: &str,
+    query_fn: F,
+    select: S,
+    options: QueryOptions<TData>,
+) -> QueryState<TSelected>
+where
+    TData: Clone + 'static,
+    TSelected: Clone + PartialEq + 'static,
+    F: 'static + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
+    S: 'static + Fn(&TData) -> TSelected,
+{
+    let selected = use_mut_ref(|| Option::<QueryState<TSelected>>::None);
+    let select = Rc::new(select);
+
+    let is_changed: IsChanged<TData> = {
+        let selected = selected.clone();
+        let select = select.clone();
+        Rc::new(move |state| Some(state.map(|data| select(data))) != *selected.borrow())
+    };
+    let state =
+        use_query_observer(query_key, query_fn, options, Some(is_changed)).map(|data| select(data));
+    *selected.borrow_mut() = Some(state.clone());
+
+    state
+}
+
+// one of the queries passed to `use_queries`
+pub struct QueryConfig<TData>
+where
+    TData: Clone + 'static,
+{
+    pub query_key: String,
+    pub query_fn: FnPtr<QueryFunctionContext, QueryResult<TData>>,
+    pub options: QueryOptions<TData>,
+}
+
+impl<TData> QueryConfig<TData>
+where
+    TData: Clone + 'static,
+{
+    pub fn new<F>(query_key: impl Into<String>, query_fn: F, options: QueryOptions<TData>) -> Self
+    where
+        F: 'static
+            + Fn(QueryFunctionContext) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
+    {
+        Self {
+            query_key: query_key.into(),
+            query_fn: FnPtr::from(query_fn),
+            options,
+        }
+    }
+}
+
+// `use_query` for a list of queries whose length can change between
+// renders, e.g. one per id. the states are in the order of `queries`.
+// `keep_previous_data` and `refetch_interval` aren't supported here
+pub fn use_queries<TData>(queries: Vec<QueryConfig<TData>>) -> Vec<QueryState<TData>>
+where
+    TData: Clone + 'static,
+{
+    let mut client = use_query_client();
+
+    let rerender = {
+        let c = use_state(|| 0);
+        move || {
+            c.set(*c + 1);
+        }
+    };
+    let observers_ref = use_mut_ref(Vec::<utils::Subscriber<TData>>::new);
+    let query_keys = queries
+        .iter()
+        .map(|query| query.query_key.clone())
+        .collect::<Vec<_>>();
+
+    let generation = use_mut_ref(|| 0_u32);
+
+    let is_current = observers_ref
+        .borrow()
+        .iter()
+        .map(|observer| observer.query_key())
+        .eq(query_keys.iter().cloned())
+        && !observers_ref
+            .borrow()
+            .iter()
+            .any(|observer| observer.is_removed());
+    if !is_current {
+        *generation.borrow_mut() += 1;
+        // observers of keys that are still in the list are kept
+        let mut previous = observers_ref.take();
+        let observers = queries
+            .iter()
+            .map(|query| {
+                match previous.iter().position(|observer| {
+                    observer.query_key() == query.query_key && !observer.is_removed()
+                }) {
+                    Some(index) => previous.swap_remove(index),
+                    None => {
+                        let options = query.options.resolve(
+                            &client,
+                            &query.query_key,
+                            query.query_fn.clone(),
+                        );
+                        utils::create_query_observer(&mut client, options)
+                    }
+                }
+            })
+            .collect();
+        *observers_ref.borrow_mut() = observers;
+    }
+
+    {
+        let observers_ref = observers_ref.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                let mut observers = observers_ref.borrow().clone();
+                let cb = Callback::<()>::from(move |_| rerender());
+                let subscriptions = observers
+                    .iter_mut()
+                    .map(|observer| observer.subscribe(cb.clone()))
+                    .collect::<Vec<_>>();
+
+                move || std::mem::drop(subscriptions)
+            },
+            (query_keys, *generation.borrow()),
+        );
+    }
+
+    let observers = observers_ref.borrow();
+    observers
+        .iter()
+        .zip(queries)
+        .map(|(observer, query)| {
+            let result = observer.get_result();
+            match (&result.status, query.options.placeholder_data) {
+                (Status::Idle | Status::Loading, Some(placeholder)) => QueryState {
+                    status: Status::Success(Rc::new(placeholder)),
+                    is_placeholder_data: true,
+                    ..result
+                },
+                _ => result,
+            }
+        })
+        .collect()
+}
+
+// stable string form of a key type, used as the cache key and therefore
+// also for matching, persistence and devtools. it has to be the same for
+// equal values across renders and reloads
+pub trait QueryKeyHash {
+    fn query_key_hash(&self) -> String;
+}
+
+// quoted, with `"` and `\` escaped so the part can't run into the ones
+// around it. everything else is kept as is, unlike with `Debug`
+impl QueryKeyHash for str {
+    fn query_key_hash(&self) -> String {
+        let mut hash = String::with_capacity(self.len() + 2);
+        hash.push('"');
+        for c in self.chars() {
+            if c == '"' || c == '\\' {
+                hash.push('\\');
+            }
+            hash.push(c);
+        }
+        hash.push('"');
+        hash
+    }
+}
+
+impl QueryKeyHash for String {
+    fn query_key_hash(&self) -> String {
+        self.as_str().query_key_hash()
+    }
+}
+
+// quoted like a one-character string, so `'1'` and `1` are different keys
+impl QueryKeyHash for char {
+    fn query_key_hash(&self) -> String {
+        self.encode_utf8(&mut [0; 4]).query_key_hash()
+    }
+}
+
+impl<T: QueryKeyHash + ?Sized> QueryKeyHash for &T {
+    fn query_key_hash(&self) -> String {
+        (**self).query_key_hash()
+    }
+}
+
+macro_rules! impl_query_key_hash_display {
+    ($($ty:ty),*) => {
+        $(
+            impl QueryKeyHash for $ty {
+                fn query_key_hash(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_query_key_hash_display!(bool, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl QueryKeyHash for () {
+    fn query_key_hash(&self) -> String {
+        String::from("[]")
+    }
+}
+
+impl<T: QueryKeyHash> QueryKeyHash for Option<T> {
+    fn query_key_hash(&self) -> String {
+        match self {
+            Some(value) => value.query_key_hash(),
+            None => String::from("null"),
+        }
+    }
+}
+
+impl<T: QueryKeyHash> QueryKeyHash for [T] {
+    fn query_key_hash(&self) -> String {
+        let items = self
+            .iter()
+            .map(QueryKeyHash::query_key_hash)
+            .collect::<Vec<_>>();
+        format!("[{}]", items.join(","))
+    }
+}
+
+impl<T: QueryKeyHash> QueryKeyHash for Vec<T> {
+    fn query_key_hash(&self) -> String {
+        self.as_slice().query_key_hash()
+    }
+}
+
+macro_rules! impl_query_key_hash_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<$($name: QueryKeyHash),+> QueryKeyHash for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn query_key_hash(&self) -> String {
+                    let ($($name,)+) = self;
+                    format!("[{}]", [$($name.query_key_hash()),+].join(","))
+                }
+            }
+        )*
+    };
+}
+
+impl_query_key_hash_tuple!((A), (A, B), (A, B, C), (A, B, C, D));
+
+// a hierarchical key, e.g. `QueryKey::from(("posts", id))`. its hash,
+// `["posts",1]`, is the cache key, so anything taking a `&str` key takes
+// `&key.hash()`. every part hashes to a self-delimiting form, which is what
+// makes matching keys by their leading parts on the hash sound
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct QueryKey {
+    parts: Vec<String>,
+}
+
+impl QueryKey {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // appends a part, e.g. `QueryKey::from("posts").push(id).push("comments")`
+    pub fn push(mut self, part: impl QueryKeyHash) -> Self {
+        self.parts.push(part.query_key_hash());
+        self
+    }
+
+    // the hashed parts
+    pub fn parts(&self) -> &[String] {
+        &self.parts
+    }
+
+    pub fn hash(&self) -> String {
+        format!("[{}]", self.parts.join(","))
+    }
+
+    // the key a `hash` came from, `None` if it isn't one (e.g. a plain
+    // string key)
+    pub fn parse(hash: &str) -> Option<Self> {
+        let inner = hash.strip_prefix('[')?.strip_suffix(']')?;
+        let mut parts = vec![];
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut start = 0;
+
+        for (index, c) in inner.char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '[' => depth += 1,
+                ']' if depth == 0 => return None,
+                ']' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(inner[start..index].to_string());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        if in_string || depth != 0 {
+            return None;
+        }
+        if !inner.is_empty() {
+            parts.push(inner[start..].to_string());
+        }
+        if parts.iter().any(String::is_empty) {
+            return None;
+        }
+
+        Some(Self { parts })
+    }
+
+    // whether the hashed `query_key` starts with every part of this key,
+    // `["posts"]` matches `["posts"]` and `["posts",1]` but not `["postsx"]`
+    pub fn is_prefix_of(&self, query_key: &str) -> bool {
+        let hash = self.hash();
+        let prefix = &hash[..hash.len() - 1];
+        match query_key.strip_prefix(prefix) {
+            // the empty key is a prefix of every structured key
+            Some(rest) => self.parts.is_empty() || rest.starts_with(',') || rest == "]",
+            None => false,
+        }
+    }
+}
+
+impl QueryKeyHash for QueryKey {
+    fn query_key_hash(&self) -> String {
+        self.hash()
+    }
+}
+
+impl fmt::Display for QueryKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.hash())
+    }
+}
+
+impl From<&str> for QueryKey {
+    fn from(part: &str) -> Self {
+        Self::new().push(part)
+    }
+}
+
+impl From<String> for QueryKey {
+    fn from(part: String) -> Self {
+        Self::new().push(part)
+    }
+}
+
+macro_rules! impl_query_key_from_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<$($name: QueryKeyHash),+> From<($($name,)+)> for QueryKey {
+                #[allow(non_snake_case)]
+                fn from(($($name,)+): ($($name,)+)) -> Self {
+                    Self::new()$(.push($name))+
+                }
+            }
+        )*
+    };
+}
+
+impl_query_key_from_tuple!((A), (A, B), (A, B, C), (A, B, C, D));
+
+type KeyPredicate = Rc<dyn Fn(&str) -> bool>;
+
+// which queries a bulk operation (`invalidate_queries`, `refetch_queries`,
+// `cancel_queries`, ...) applies to. every condition that's set has to
+// hold, `QueryFilters::all()` matches every query. a plain key converts
+// into an exact match, a `QueryKey` into everything below it
+#[derive(Clone, Default)]
+pub struct QueryFilters {
+    exact: Option<String>,
+    prefix: Option<String>,
+    key: Option<QueryKey>,
+    predicate: Option<KeyPredicate>,
+    stale: Option<bool>,
+    active: Option<bool>,
+    fetching: Option<bool>,
+}
+
+impl QueryFilters {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn exact(query_key: impl Into<String>) -> Self {
+        Self {
+            exact: Some(query_key.into()),
+            ..Self::default()
+        }
+    }
+
+    // every key starting with `prefix`
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(prefix.into()),
+            ..Self::default()
+        }
+    }
+
+    // every key below `key` in its hierarchy, including `key` itself
+    pub fn key(key: QueryKey) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::default()
+        }
+    }
+
+    pub fn predicate(predicate: impl Fn(&str) -> bool + 'static) -> Self {
+        Self {
+            predicate: Some(Rc::new(predicate)),
+            ..Self::default()
+        }
+    }
+
+    pub fn stale(mut self, stale: bool) -> Self {
+        self.stale = Some(stale);
+        self
+    }
+
+    // active queries have at least one subscriber
+    pub fn active(mut self, active: bool) -> Self {
+        self.active = Some(active);
+        self
+    }
+
+    // paused fetches count as fetching
+    pub fn fetching(mut self, fetching: bool) -> Self {
+        self.fetching = Some(fetching);
+        self
+    }
+
+    pub fn matches(&self, query: &dyn AnyQuery) -> bool {
+        let query_key = query.query_key();
+
+        self.exact.as_ref().is_none_or(|exact| *exact == query_key)
+            && self
+                .prefix
+                .as_ref()
+                .is_none_or(|prefix| query_key.starts_with(prefix.as_str()))
+            && self
+                .key
+                .as_ref()
+                .is_none_or(|key| key.is_prefix_of(&query_key))
+            && self
+                .predicate
+                .as_ref()
+                .is_none_or(|predicate| predicate(&query_key))
+            && self.stale.is_none_or(|stale| query.is_stale() == stale)
+            && self
+                .active
+                .is_none_or(|active| (query.subscriber_count() > 0) == active)
+            && self.fetching.is_none_or(|fetching| {
+                (query.status().fetch_status != FetchStatus::Idle) == fetching
+            })
+    }
+}
+
+impl From<&str> for QueryFilters {
+    fn from(query_key: &str) -> Self {
+        Self::exact(query_key)
+    }
+}
+
+impl From<&String> for QueryFilters {
+    fn from(query_key: &String) -> Self {
+        Self::exact(query_key.as_str())
+    }
+}
+
+impl From<String> for QueryFilters {
+    fn from(query_key: String) -> Self {
+        Self::exact(query_key)
+    }
+}
+
+impl From<QueryKey> for QueryFilters {
+    fn from(key: QueryKey) -> Self {
+        Self::key(key)
+    }
+}
+
+impl From<&QueryKey> for QueryFilters {
+    fn from(key: &QueryKey) -> Self {
+        Self::key(key.clone())
+    }
+}
+
+// the key for `params` under `base_key`, e.g. `["post",1]`, so
+// `invalidate_queries(QueryKey::from(base_key))` reaches every variant
+pub fn params_key<TParams: QueryKeyHash>(base_key: &str, params: &TParams) -> QueryKey {
+    QueryKey::from(base_key).push(params)
+}
+
+// like `use_query`, but the key is derived from `params`, which are passed
+// to `query_fn` on every fetch along with its `QueryFunctionContext`
+pub fn use_query_with_params<TData, TParams, F>(
+    base_key: &str,
+    params: TParams,
+    query_fn: F,
+    options: QueryOptions<TData>,
+) -> utils::QueryState<TData>
+where
+    TData: Clone + 'static,
+    TParams: Clone + QueryKeyHash + 'static,
+    F: 'static
+        + Fn(TParams, QueryFunctionContext) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
+{
+    let query_key = params_key(base_key, &params).hash();
+
+    use_query(
+        &query_key,
+        move |context| query_fn(params.clone(), context),
+        options,
+    )
+}
+
+// only re-renders when the status or `fetch_status` of `query_key`
+// changes, never fetches by itself
+pub fn use_query_status(query_key: &str) -> QueryStatusState {
+    let client = use_query_client();
+    let status = use_state(|| client.get_query_status(query_key));
+
+    {
+        let status = status.clone();
+        let query_key = query_key.to_string();
+
+        use_effect_with_deps(
+            move |query_key| {
+                let mut client = client.clone();
+                let query_key = query_key.clone();
+                let current = Rc::new(Cell::new(client.get_query_status(&query_key)));
+                if *status != current.get() {
+                    status.set(current.get());
+                }
+
+                let on_change = {
+                    let client = client.clone();
+                    Callback::from(move |_: ()| {
+                        let client = client.clone();
+                        let query_key = query_key.clone();
+                        let current = current.clone();
+                        let status = status.clone();
+
+                        // the notifying query is still borrowed at this point
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let next = client.get_query_status(&query_key);
+                            if next != current.get() {
+                                current.set(next);
+                                status.set(next);
+                            }
+                        });
+                    })
+                };
+                client.subscribe(on_change.clone());
+
+                move || client.unsubscribe(on_change)
+            },
+            query_key,
+        );
+    }
+
+    *status
+}
+
+// the pages of an infinite query, with the param each was fetched with
+#[derive(Clone, PartialEq, Debug)]
+pub struct InfiniteData<TPage, TParam> {
+    pub pages: Vec<TPage>,
+    pub page_params: Vec<TParam>,
+}
+
+// how an infinite query's pages are stored in, and read back from, its
+// data type, e.g. an enum variant holding them next to other state
+pub trait InfiniteQueryData<TPage, TParam>: Sized {
+    fn from_infinite(data: InfiniteData<TPage, TParam>) -> Self;
+    fn as_infinite(&self) -> Option<&InfiniteData<TPage, TParam>>;
+}
+
+impl<TPage, TParam> InfiniteQueryData<TPage, TParam> for InfiniteData<TPage, TParam> {
+    fn from_infinite(data: InfiniteData<TPage, TParam>) -> Self {
+        data
+    }
+
+    fn as_infinite(&self) -> Option<&InfiniteData<TPage, TParam>> {
+        Some(self)
+    }
+}
+
+type FetchNextPage = Rc<dyn Fn() -> Pin<Box<dyn Future<Output = QueryResult<()>>>>>;
+
+pub struct UseInfiniteQueryHandle<TData, TPage, TParam>
+where
+    TData: Clone + 'static,
+{
+    // the query as a whole, only its first page failing to (re)fetch puts
+    // it into `Status::Error`
+    pub state: QueryState<TData>,
+    pub has_next_page: bool,
+    pub is_fetching_next_page: bool,
+    // the last `fetch_next_page` failure, the loaded pages are kept and
+    // calling `fetch_next_page` again retries just that page
+    pub next_page_error: Option<String>,
+    fetch_next: FetchNextPage,
+    _pages: PhantomData<(TPage, TParam)>,
+}
+
+impl<TData, TPage, TParam> UseInfiniteQueryHandle<TData, TPage, TParam>
+where
+    TData: Clone + InfiniteQueryData<TPage, TParam> + 'static,
+{
+    pub fn pages(&self) -> &[TPage] {
+        match &self.state.status {
+            Status::Success(data) => data
+                .as_infinite()
+                .map(|data| data.pages.as_slice())
+                .unwrap_or_default(),
+            _ => &[],
+        }
+    }
+
+    pub fn fetch_next_page(&self) {
+        let fetch = (self.fetch_next)();
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = fetch.await;
+        });
+    }
+
+    // resolves with the error of the page if it failed
+    pub fn fetch_next_page_async(&self) -> impl Future<Output = QueryResult<()>> {
+        (self.fetch_next)()
+    }
+}
+
+impl<TData, TPage, TParam> Clone for UseInfiniteQueryHandle<TData, TPage, TParam>
+where
+    TData: Clone + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            has_next_page: self.has_next_page,
+            is_fetching_next_page: self.is_fetching_next_page,
+            next_page_error: self.next_page_error.clone(),
+            fetch_next: self.fetch_next.clone(),
+            _pages: PhantomData,
+        }
+    }
+}
+
+// accumulates pages under a single key. `get_next_page_param` gets the last
+// page and every loaded page and returns the param for the next one, or
+// `None` once there are no more pages. refetching refetches every loaded page.
+// `query_fn` gets the page param and the `QueryFunctionContext` of the fetch.
+// pages are fetched like the rest of the query, so fetching the next page
+// while the query is refetching waits for the refetch instead, and calling
+// `fetch_next_page` while a page is loading does nothing
+pub fn use_infinite_query<TData, TPage, TParam, F, G>(
+    query_key: &str,
+    initial_page_param: TParam,
+    query_fn: F,
+    get_next_page_param: G,
+    options: QueryOptions<TData>,
+) -> UseInfiniteQueryHandle<TData, TPage, TParam>
+where
+    TData: Clone + InfiniteQueryData<TPage, TParam> + 'static,
+    TPage: Clone + 'static,
+    TParam: Clone + 'static,
+    F: 'static
+        + Fn(TParam, QueryFunctionContext) -> Pin<Box<dyn Future<Output = QueryResult<TPage>>>>,
+    G: 'static + Fn(&TPage, &[TPage]) -> Option<TParam>,
+{
+    let client = use_query_client();
+    let query_fn = Rc::new(query_fn);
+    let get_next_page_param = Rc::new(get_next_page_param);
+    let is_fetching_next_page = use_state(|| false);
+    // set until the page settles, the state above only updates on render
+    let fetching_next = use_mut_ref(|| false);
+    let next_page_error = use_state(|| Option::<String>::None);
+
+    let state = {
+        let client = client.clone();
+        let query_key = query_key.to_string();
+        let query_fn = query_fn.clone();
+
+        use_query(
+            &query_key.clone(),
+            move |context| {
+                let client = client.clone();
+                let query_key = query_key.clone();
+                let query_fn = query_fn.clone();
+                let initial_page_param = initial_page_param.clone();
+
+                Box::pin(async move {
+                    let cached = client
+                        .get_query_data::<TData>(&query_key)
+                        .and_then(|data| data.as_infinite().cloned());
+                    let params = match &cached {
+                        Some(cached) if !cached.page_params.is_empty() => {
+                            cached.page_params.clone()
+                        }
+                        _ => vec![initial_page_param],
+                    };
+
+                    let mut data = InfiniteData {
+                        pages: vec![],
+                        page_params: vec![],
+                    };
+                    for (index, param) in params.into_iter().enumerate() {
+                        match query_fn(param.clone(), context.clone()).await {
+                            Ok(page) => {
+                                data.pages.push(page);
+                                data.page_params.push(param);
+                            }
+                            Err(err) if index == 0 => return Err(err),
+                            // keep the pages that were refetched and the
+                            // previously loaded ones after them
+                            Err(_) => {
+                                if let Some(cached) = cached {
+                                    data.pages.extend(cached.pages.into_iter().skip(index));
+                                    data.page_params
+                                        .extend(cached.page_params.into_iter().skip(index));
+                                }
+                                break;
+                            }
+                        }
+                    }
+
+                    Ok(TData::from_infinite(data))
+                })
+            },
+            options,
+        )
+    };
+
+    let next_page_param = match &state.status {
+        Status::Success(data) => data.as_infinite().and_then(|data| {
+            data.pages
+                .last()
+                .and_then(|last| get_next_page_param(last, &data.pages))
+        }),
+        _ => None,
+    };
+
+    let fetch_next: FetchNextPage = {
+        let client = client.clone();
+        let query_key = query_key.to_string();
+        let is_fetching_next_page = is_fetching_next_page.clone();
+        let next_page_error = next_page_error.clone();
+
+        Rc::new(move || {
+            let client = client.clone();
+            let query_key = query_key.clone();
+            let query_fn = query_fn.clone();
+            let get_next_page_param = get_next_page_param.clone();
+            let is_fetching_next_page = is_fetching_next_page.clone();
+            let fetching_next = fetching_next.clone();
+            let next_page_error = next_page_error.clone();
+
+            Box::pin(async move {
+                if *fetching_next.borrow() {
+                    return Ok(());
+                }
+                let Some(query) = client.find_query::<TData>(&query_key) else {
+                    return Ok(());
+                };
+                let param = client.get_query_data::<TData>(&query_key).and_then(|data| {
+                    let data = data.as_infinite()?;
+                    get_next_page_param(data.pages.last()?, &data.pages)
+                });
+                let Some(param) = param else {
+                    return Ok(());
+                };
+
+                // the error of the page's last attempt, the query keeps its
+                // data if the page fails
+                let page_error = Rc::new(RefCell::new(None));
+                // the pages loaded by the time the next one arrives, plus
+                // that one
+                let page_fn = {
+                    let client = client.clone();
+                    let query_key = query_key.clone();
+                    let page_error = page_error.clone();
+
+                    FnPtr::from(move |context: QueryFunctionContext| {
+                        let page = query_fn(param.clone(), context);
+                        let client = client.clone();
+                        let query_key = query_key.clone();
+                        let param = param.clone();
+                        let page_error = page_error.clone();
+
+                        Box::pin(async move {
+                            let page = page.await;
+                            *page_error.borrow_mut() = page.as_ref().err().cloned();
+                            let page = page?;
+                            let mut data = client
+                                .get_query_data::<TData>(&query_key)
+                                .and_then(|data| data.as_infinite().cloned())
+                                .unwrap_or(InfiniteData {
+                                    pages: vec![],
+                                    page_params: vec![],
+                                });
+                            data.pages.push(page);
+                            data.page_params.push(param);
+                            Ok(TData::from_infinite(data))
+                        })
+                            as Pin<Box<dyn Future<Output = QueryResult<TData>>>>
+                    })
+                };
+
+                *fetching_next.borrow_mut() = true;
+                is_fetching_next_page.set(true);
+                Query::fetch_with(query, page_fn, true).await;
+                is_fetching_next_page.set(false);
+                *fetching_next.borrow_mut() = false;
+
+                let error = page_error.borrow_mut().take();
+                next_page_error.set(error.clone());
+                match error {
+                    Some(err) => Err(err),
+                    None => Ok(()),
+                }
+            })
+        })
+    };
+
+    UseInfiniteQueryHandle {
+        state,
+        has_next_page: next_page_param.is_some(),
+        is_fetching_next_page: *is_fetching_next_page,
+        next_page_error: (*next_page_error).clone(),
+        fetch_next,
+        _pages: PhantomData,
+    }
+}
+
+// read-only access to the cached data of `query_key`. never fetches and
+// only re-renders when the data is updated, not on fetch status changes
+pub fn use_query_data<TData>(query_key: &str) -> Option<Rc<TData>>
+where
+    TData: Clone + 'static,
+{
+    let client = use_query_client();
+    let data = use_state(|| {
+        client
+            .get_query_data_with_updated_at::<TData>(query_key)
+            .map(|(data, updated_at)| (Rc::new(data), updated_at))
+    });
+
+    {
+        let data = data.clone();
+        let query_key = query_key.to_string();
+
+        use_effect_with_deps(
+            move |query_key| {
+                let mut client = client.clone();
+                let query_key = query_key.clone();
+                let updated_at = |client: &QueryClient, query_key: &str| {
+                    client
+                        .get_query_data_with_updated_at::<TData>(query_key)
+                        .map(|(_, updated_at)| updated_at)
+                };
+                let current = Rc::new(Cell::new(updated_at(&client, &query_key)));
+                if data.as_ref().map(|(_, updated_at)| *updated_at) != current.get() {
+                    data.set(
+                        client
+                            .get_query_data_with_updated_at(&query_key)
+                            .map(|(value, at)| (Rc::new(value), at)),
+                    );
+                }
+
+                let on_change = {
+                    let client = client.clone();
+                    Callback::from(move |_: ()| {
+                        let client = client.clone();
+                        let query_key = query_key.clone();
+                        let current = current.clone();
+                        let data = data.clone();
+
+                        // the notifying query is still borrowed at this point
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let next = client.get_query_data_with_updated_at::<TData>(&query_key);
+                            let next_updated_at = next.as_ref().map(|(_, at)| *at);
+                            if next_updated_at != current.get() {
+                                current.set(next_updated_at);
+                                data.set(next.map(|(value, at)| (Rc::new(value), at)));
+                            }
+                        });
+                    })
+                };
+                client.subscribe(on_change.clone());
+
+                move || client.unsubscribe(on_change)
+            },
+            query_key,
+        );
+    }
+
+    data.as_ref().map(|(data, _)| data.clone())
+}
+
+// number of fetching queries matching `filters`, re-renders only when it
+// changes
+pub fn use_is_fetching(filters: impl Into<QueryFilters>) -> usize {
+    let client = use_query_client();
+    let filters = filters.into();
+    let count = {
+        let filters = filters.clone();
+        use_state(|| client.is_fetching(filters))
+    };
+
+    {
+        let count = count.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                let mut client = client.clone();
+                let current = Rc::new(Cell::new(*count));
+
+                let on_change = {
+                    let client = client.clone();
+                    Callback::from(move |_: ()| {
+                        let client = client.clone();
+                        let filters = filters.clone();
+                        let current = current.clone();
+                        let count = count.clone();
+
+                        // the notifying query is still borrowed at this point
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let next = client.is_fetching(filters);
+                            if next != current.get() {
+                                current.set(next);
+                                count.set(next);
+                            }
+                        });
+                    })
+                };
+                client.subscribe(on_change.clone());
+
+                move || client.unsubscribe(on_change)
+            },
+            (),
+        );
+    }
+
+    *count
+}
+
+// the mutations in the client's `MutationCache`: running, paused and
+// recently settled ones, until `QueryClient::mutation_gc_time` removes
+// them. e.g. to list pending uploads. re-renders when one changes or is
+// garbage collected
+pub fn use_mutation_state() -> Vec<Mutation> {
+    let mutation_cache = use_query_client().mutation_cache();
+    let mutations = use_state(|| mutation_cache.mutations());
+
+    {
+        let mutations = mutations.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                let on_change = {
+                    let mutation_cache = mutation_cache.clone();
+                    Callback::from(move |_: ()| mutations.set(mutation_cache.mutations()))
+                };
+                let on_mutation = {
+                    let on_change = on_change.clone();
+                    Callback::from(move |_: Mutation| on_change.emit(()))
+                };
+                let on_removed = Callback::from(move |_: u32| on_change.emit(()));
+                mutation_cache.subscribe(on_mutation.clone());
+                mutation_cache.subscribe_removed(on_removed.clone());
+
+                move || {
+                    mutation_cache.unsubscribe(on_mutation);
+                    mutation_cache.unsubscribe_removed(on_removed);
+                }
+            },
+            (),
+        );
+    }
+
+    (*mutations).clone()
+}
+
+// the client's `CacheStats`, re-renders when they change
+pub fn use_cache_stats() -> CacheStats {
+    let client = use_query_client();
+    let stats = use_state(|| client.stats());
+
+    {
+        let stats = stats.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                let current = Rc::new(RefCell::new((*stats).clone()));
+
+                let on_event = {
+                    let client = client.clone();
+                    Callback::from(move |_: QueryEvent| {
+                        let next = client.stats();
+                        if next != *current.borrow() {
+                            *current.borrow_mut() = next.clone();
+                            stats.set(next);
+                        }
+                    })
+                };
+                client.subscribe_events(on_event.clone());
+
+                move || client.unsubscribe_events(on_event)
+            },
+            (),
+        );
+    }
+
+    (*stats).clone()
+}
+
+// a callback for a global "retry failed requests" button, refetches every
+// errored query matching `filters`
+pub fn use_retry_all_errors(filters: impl Into<QueryFilters>) -> Callback<()> {
+    let client = use_query_client();
+    let filters = filters.into();
+
+    Callback::from(move |_| {
+        let client = client.clone();
+        let filters = filters.clone();
+
+        wasm_bindgen_futures::spawn_local(async move {
+            client.retry_errored(filters).await;
+        });
+    })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct QueryClientProviderProps {
+    pub client: QueryClient,
+    #[prop_or_default]
+    pub children: Children,
+}
+
+pub fn use_query_client() -> QueryClient {
+    use_context::<QueryClient>().expect("QueryContext not found")
+}
+
+#[function_component(QueryClientProvider)]
+pub fn query_client_provider(props: &QueryClientProviderProps) -> Html {
+    let client = props.client.clone();
+
+    use_effect_with_deps(
+        move |client: &QueryClient| {
+            let cleanups = client.mount_plugins();
+
+            move || {
+                for cleanup in cleanups.into_iter().rev() {
+                    cleanup()
+                }
+            }
+        },
+        client,
+    );
+
+    html! {
+        <ContextProvider<QueryClient> context={props.client.clone()}>
+            <ContextProvider<MutationCache> context={props.client.mutation_cache()}>
+                { for props.children.iter() }
+            </ContextProvider<MutationCache>>
+        </ContextProvider<QueryClient>>
+    }
+}
+
+#[cfg(feature = "hydration")]
+pub mod hydration {
+    use crate::{Query, QueryClient, Status};
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+    use std::any::{Any, TypeId};
+    use std::cell::RefCell;
+    use std::fmt::Debug;
+    use std::rc::Rc;
+
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct DehydratedQuery<TData> {
+        pub query_key: String,
+        pub data: TData,
+        pub updated_at: Option<i64>,
+    }
+
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct DehydratedState<TData> {
+        pub queries: Vec<DehydratedQuery<TData>>,
+    }
+
+    // id of the `<script type="application/json">` element holding a
+    // dehydrated state embedded in index.html
+    pub const EMBEDDED_STATE_ID: &str = "yew-query-state";
+
+    impl<TData> DehydratedState<TData>
+    where
+        TData: Serialize,
+    {
+        // the element to inline into index.html at build or deploy time,
+        // e.g. in place of a placeholder in the page Trunk emits
+        pub fn to_script_tag(&self) -> Result<String, String> {
+            let json = serde_json::to_string(self).map_err(|err| err.to_string())?;
+
+            // a `</script>` inside a string would end the element early
+            Ok(format!(
+                r#"<script type="application/json" id="{}">{}</script>"#,
+                EMBEDDED_STATE_ID,
+                json.replace("</", "<\\/")
+            ))
         }
+    }
 
-        pub fn subscribe(&mut self, callback: Callback<()>) {
-            web_sys::console::log_1(&"`subscribe`: TRYING TO BORROW".into());
-            let mut x = (*self.query).borrow_mut();
-            x.subscribe(self.clone(), callback);
-            std::mem::drop(x);
-            self.fetch();
+    // every method only handles queries holding `TData`, a client caching
+    // several data types (de)hydrates each of them separately
+    impl QueryClient {
+        // fills in `QueryInfo::size` for queries holding `TData`
+        pub fn measure_size<TData>(self) -> Self
+        where
+            TData: Serialize + 'static,
+        {
+            self.sizers.borrow_mut().insert(
+                TypeId::of::<TData>(),
+                Rc::new(|data: &dyn Any| {
+                    let data = data.downcast_ref::<TData>()?;
+                    serde_json::to_string(data).ok().map(|json| json.len())
+                }),
+            );
+            self
         }
 
-        pub fn unsubscribe(&mut self, callback: Callback<()>) {
-            (*self.query).borrow_mut().unsubscribe(callback)
+        // snapshots the successful queries whose key passes `filter`, e.g.
+        // only the ones the initial route needs
+        pub fn dehydrate<TData>(&self, filter: impl Fn(&str) -> bool) -> DehydratedState<TData>
+        where
+            TData: Clone + Serialize + 'static,
+        {
+            let queries = self
+                .queries
+                .borrow()
+                .values()
+                .filter_map(|query| {
+                    let query = query
+                        .clone()
+                        .into_any()
+                        .downcast::<RefCell<Query<TData>>>()
+                        .ok()?;
+                    let query = query.borrow();
+                    match &query.state.status {
+                        Status::Success(data) if filter(&query.query_key) => {
+                            Some(DehydratedQuery {
+                                query_key: query.query_key.clone(),
+                                data: (**data).clone(),
+                                updated_at: query.state.last_updated,
+                            })
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            DehydratedState { queries }
         }
 
-        pub fn fetch(&mut self) {
-            web_sys::console::log_1(&"`fetch`: TRYING TO BORROW MUT".into());
-            let query = Rc::clone(&self.query);
-            let query = (*query).borrow_mut();
-            if query.state.last_updated.is_none()
-                || ((now()) - query.state.last_updated.unwrap() > self.stale_time)
-            {
-                let mut query = query.clone();
-                wasm_bindgen_futures::spawn_local(async move {
-                    web_sys::console::log_1(&"`spawn_local`: TRYING TO BORROWING MUT".into());
-                    // >> ISSUE OCCURS HERE
-                    query.fetch().await;
-                    web_sys::console::log_1(&"`spawn_local`: TRYING TO DROP MUT".into());
-                });
+        pub fn hydrate<TData>(&self, state: DehydratedState<TData>)
+        where
+            TData: Clone + 'static,
+        {
+            for query in state.queries {
+                let DehydratedQuery {
+                    query_key,
+                    data,
+                    updated_at,
+                } = query;
+
+                self.seed_query(query_key, Box::new(move || Some((data, updated_at))));
+            }
+        }
+
+        // registers a secondary payload (a serialized `DehydratedState`)
+        // whose entries are only deserialized once their query is requested
+        pub fn hydrate_lazily<TData>(&self, payload: &str) -> Result<(), String>
+        where
+            TData: Clone + DeserializeOwned + 'static,
+        {
+            let state = serde_json::from_str::<DehydratedState<serde_json::Value>>(payload)
+                .map_err(|err| err.to_string())?;
+
+            for query in state.queries {
+                let DehydratedQuery {
+                    query_key,
+                    data,
+                    updated_at,
+                } = query;
+
+                self.seed_query::<TData>(
+                    query_key,
+                    Box::new(move || {
+                        serde_json::from_value(data)
+                            .ok()
+                            .map(|data| (data, updated_at))
+                    }),
+                );
+            }
+
+            Ok(())
+        }
+
+        // lazily hydrates the state embedded with `to_script_tag`, meant to
+        // be called before the first render. `Ok(false)` if the page has none
+        pub fn hydrate_from_document<TData>(&self) -> Result<bool, String>
+        where
+            TData: Clone + DeserializeOwned + 'static,
+        {
+            let element = web_sys::window()
+                .expect("Couldn't access `window`")
+                .document()
+                .expect("Couldn't access `document`")
+                .get_element_by_id(EMBEDDED_STATE_ID);
+
+            match element.and_then(|element| element.text_content()) {
+                Some(payload) => self.hydrate_lazily::<TData>(&payload).map(|_| true),
+                None => Ok(false),
+            }
+        }
+    }
+}
+
+// co-located server functions, see `server_fn!`
+#[cfg(feature = "server-fn")]
+pub mod server_fn {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    pub mod __private {
+        pub use serde_json;
+    }
+
+    // what the client build of a server function compiles into: POSTs the
+    // JSON encoded arguments to `endpoint` and decodes the JSON response
+    pub async fn call<TArgs, TData>(endpoint: &str, args: &TArgs) -> Result<TData, String>
+    where
+        TArgs: Serialize,
+        TData: DeserializeOwned,
+    {
+        let body = serde_json::to_string(args).map_err(|err| err.to_string())?;
+
+        let init = web_sys::RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&body.into());
+        let request = web_sys::Request::new_with_str_and_init(endpoint, &init)
+            .map_err(|_| format!("couldn't build request for {:?}", endpoint))?;
+        request
+            .headers()
+            .set("Content-Type", "application/json")
+            .map_err(|_| String::from("couldn't set `Content-Type`"))?;
+
+        let window = web_sys::window().expect("Couldn't access `window`");
+        let response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(|_| format!("request to {:?} failed", endpoint))?
+            .unchecked_into::<web_sys::Response>();
+        let text = JsFuture::from(response.text().map_err(|_| "response has no body")?)
+            .await
+            .map_err(|_| String::from("couldn't read response body"))?
+            .as_string()
+            .unwrap_or_default();
+
+        if !response.ok() {
+            return Err(text);
+        }
+        serde_json::from_str(&text).map_err(|err| err.to_string())
+    }
+}
+
+// defines a server function once for both builds:
+//
+//     server_fn! {
+//         #[endpoint = "/api/get_post"]
+//         pub async fn get_post(id: usize) -> Post {
+//             db::post(id).await.map_err(|err| err.to_string())
+//         }
+//     }
+//
+// expands to a `get_post` module with the shared `ENDPOINT` and `key(&id)`
+// (the query key), and `call(id)` which runs the body natively and fetches
+// `ENDPOINT` on wasm. the native build also gets `handle(body)`, which
+// decodes the request body, runs `call` and encodes the result, for
+// mounting at `ENDPOINT` in whatever server framework is used
+#[cfg(feature = "server-fn")]
+#[macro_export]
+macro_rules! server_fn {
+    (
+        #[endpoint = $endpoint:literal]
+        $vis:vis async fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $data:ty $body:block
+    ) => {
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub const ENDPOINT: &str = $endpoint;
+
+            pub fn key($($arg: &$ty),*) -> String {
+                $crate::params_key(ENDPOINT, &($($arg,)*)).hash()
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            pub async fn call($($arg: $ty),*) -> Result<$data, String> $body
+
+            #[cfg(target_arch = "wasm32")]
+            pub async fn call($($arg: $ty),*) -> Result<$data, String> {
+                $crate::server_fn::call(ENDPOINT, &($($arg,)*)).await
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            pub async fn handle(body: &str) -> Result<String, String> {
+                use $crate::server_fn::__private::serde_json;
+
+                let ($($arg,)*): ($($ty,)*) =
+                    serde_json::from_str(body).map_err(|err| err.to_string())?;
+                let data = call($($arg),*).await?;
+                serde_json::to_string(&data).map_err(|err| err.to_string())
+            }
+        }
+    };
+}
+
+pub mod plugins {
+    #[cfg(feature = "persist")]
+    use crate::hydration::DehydratedQuery;
+    #[cfg(feature = "shared-worker")]
+    use crate::utils::InterceptedFetch;
+    #[cfg(any(feature = "devtools-bridge", feature = "shared-worker"))]
+    use crate::AnyQuery;
+    #[cfg(feature = "devtools-bridge")]
+    use crate::{FetchStatus, QueryFilters, QueryStatus};
+    #[cfg(feature = "persist")]
+    use crate::{Mutation, MutationStatusKind};
+    use crate::{QueryClient, QueryPlugin};
+    #[cfg(any(feature = "persist", feature = "shared-worker"))]
+    use serde::{de::DeserializeOwned, Serialize};
+    #[cfg(any(feature = "persist", feature = "shared-worker"))]
+    use std::{any::Any, marker::PhantomData};
+    #[cfg(feature = "shared-worker")]
+    use std::{
+        any::TypeId,
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+    };
+    use std::{cell::Cell, rc::Rc};
+    use wasm_bindgen::JsCast;
+    #[cfg(feature = "shared-worker")]
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::window;
+    #[cfg(any(feature = "devtools-bridge", feature = "persist"))]
+    use yew::Callback;
+
+    // focusing a window often fires `visibilitychange` and `focus` right
+    // after each other, only the first one refetches
+    const FOCUS_DEBOUNCE_MS: i64 = 500;
+
+    // refetches the stale mounted queries when the window regains focus
+    pub struct FocusRefetch;
+
+    impl QueryPlugin for FocusRefetch {
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let queries = client.queries.clone();
+            let last_refetch = Cell::new(i64::MIN);
+
+            let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+                // `visibilitychange` also fires when the page is hidden
+                if !crate::is_visible() {
+                    return;
+                }
+                let now = crate::now();
+                if now.saturating_sub(last_refetch.get()) < FOCUS_DEBOUNCE_MS {
+                    return;
+                }
+                last_refetch.set(now);
+
+                for query in (queries).borrow().values() {
+                    query.fetch_subscribers(|triggers| triggers.window_focus);
+                }
+            }) as Box<dyn FnMut()>);
+            let on_focus = closure.as_ref().unchecked_ref::<js_sys::Function>().clone();
+
+            let window = window().expect("Couldn't access `window`");
+
+            window
+                .add_event_listener_with_callback_and_bool("visibilitychange", &on_focus, false)
+                .unwrap();
+            window
+                .add_event_listener_with_callback_and_bool("focus", &on_focus, false)
+                .unwrap();
+
+            Box::new(move || {
+                window
+                    .remove_event_listener_with_callback("visibilitychange", &on_focus)
+                    .unwrap();
+                window
+                    .remove_event_listener_with_callback("focus", &on_focus)
+                    .unwrap();
+                // the listeners hold the closure, so it has to outlive them
+                std::mem::drop(closure);
+            })
+        }
+    }
+
+    // refetches every mounted query when the browser comes back online
+    pub struct ReconnectRefetch;
+
+    impl QueryPlugin for ReconnectRefetch {
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let queries = client.queries.clone();
+            // some browsers fire `online` on any network change, only a
+            // preceding `offline` makes it a reconnect
+            let offline = Rc::new(Cell::new(!crate::is_online()));
+
+            let online_closure = wasm_bindgen::closure::Closure::wrap(Box::new({
+                let offline = offline.clone();
+                move || {
+                    if !offline.replace(false) {
+                        return;
+                    }
+                    for query in (queries).borrow().values() {
+                        query.fetch_subscribers(|triggers| triggers.reconnect);
+                    }
+                }
+            })
+                as Box<dyn FnMut()>);
+            let on_online = online_closure
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone();
+
+            let offline_closure =
+                wasm_bindgen::closure::Closure::wrap(
+                    Box::new(move || offline.set(true)) as Box<dyn FnMut()>
+                );
+            let on_offline = offline_closure
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone();
+
+            let window = window().expect("Couldn't access `window`");
+
+            window
+                .add_event_listener_with_callback_and_bool("online", &on_online, false)
+                .unwrap();
+            window
+                .add_event_listener_with_callback_and_bool("offline", &on_offline, false)
+                .unwrap();
+
+            Box::new(move || {
+                window
+                    .remove_event_listener_with_callback("online", &on_online)
+                    .unwrap();
+                window
+                    .remove_event_listener_with_callback("offline", &on_offline)
+                    .unwrap();
+                std::mem::drop(online_closure);
+                std::mem::drop(offline_closure);
+            })
+        }
+    }
+
+    #[cfg(feature = "push")]
+    enum Transport {
+        WebSocket,
+        EventSource,
+    }
+
+    // invalidates queries when the backend pushes `{ "key": .., "exact": .. }`
+    // messages over a WebSocket or server-sent events. non-exact keys
+    // invalidate every query with that prefix
+    #[cfg(feature = "push")]
+    pub struct PushInvalidation {
+        url: String,
+        transport: Transport,
+    }
+
+    #[cfg(feature = "push")]
+    impl PushInvalidation {
+        pub fn websocket(url: &str) -> Self {
+            Self {
+                url: url.to_string(),
+                transport: Transport::WebSocket,
+            }
+        }
+
+        pub fn event_source(url: &str) -> Self {
+            Self {
+                url: url.to_string(),
+                transport: Transport::EventSource,
+            }
+        }
+    }
+
+    #[cfg(feature = "push")]
+    fn parse_invalidation(data: &str) -> Option<(String, bool)> {
+        let message = js_sys::JSON::parse(data).ok()?;
+        let key = js_sys::Reflect::get(&message, &"key".into())
+            .ok()?
+            .as_string()?;
+        let exact = js_sys::Reflect::get(&message, &"exact".into())
+            .ok()
+            .and_then(|exact| exact.as_bool())
+            .unwrap_or(true);
+
+        Some((key, exact))
+    }
+
+    #[cfg(feature = "push")]
+    impl QueryPlugin for PushInvalidation {
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let client = client.clone();
+            let on_message = wasm_bindgen::closure::Closure::wrap(Box::new(
+                move |event: web_sys::MessageEvent| {
+                    let message = event.data().as_string();
+                    if let Some((key, exact)) = message.as_deref().and_then(parse_invalidation) {
+                        let client = client.clone();
+                        wasm_bindgen_futures::spawn_local(async move {
+                            if exact {
+                                client.invalidate_queries(&key).await
+                            } else {
+                                client.invalidate_queries_by_prefix(&key).await
+                            }
+                        });
+                    }
+                },
+            )
+                as Box<dyn FnMut(web_sys::MessageEvent)>);
+
+            match self.transport {
+                Transport::WebSocket => {
+                    let socket =
+                        web_sys::WebSocket::new(&self.url).expect("Couldn't open `WebSocket`");
+                    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+                    Box::new(move || {
+                        socket.set_onmessage(None);
+                        let _ = socket.close();
+                        std::mem::drop(on_message);
+                    })
+                }
+                Transport::EventSource => {
+                    let source =
+                        web_sys::EventSource::new(&self.url).expect("Couldn't open `EventSource`");
+                    source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+                    Box::new(move || {
+                        source.set_onmessage(None);
+                        source.close();
+                        std::mem::drop(on_message);
+                    })
+                }
+            }
+        }
+    }
+
+    // keeps the cache in sync across every window and iframe of the origin
+    // through a SharedWorker running `SharedWorkerCache::WORKER_SCRIPT`,
+    // which has to be served from `script_url` (e.g. copied by Trunk). the
+    // worker keeps the latest data per key and hands out fetch leases, so
+    // only one window fetches a key while the others wait for its result.
+    // only queries holding `TData` are synced, register one per data type
+    #[cfg(feature = "shared-worker")]
+    pub struct SharedWorkerCache<TData> {
+        script_url: String,
+        port: Rc<RefCell<Option<web_sys::MessagePort>>>,
+        // lease requests waiting for the worker's answer, by request id
+        leases: Rc<RefCell<HashMap<u32, js_sys::Function>>>,
+        // windows waiting for another window's fetch, by key
+        waiting: Rc<RefCell<HashMap<String, Vec<js_sys::Function>>>>,
+        // results that came from another window and mustn't be sent back
+        received: Rc<RefCell<HashSet<String>>>,
+        next_id: Rc<Cell<u32>>,
+        _data: PhantomData<TData>,
+    }
+
+    #[cfg(feature = "shared-worker")]
+    impl<TData> SharedWorkerCache<TData> {
+        // to be served at `script_url`. a lease older than `LEASE_MS` is
+        // assumed to belong to a window that was closed mid-fetch
+        pub const WORKER_SCRIPT: &'static str = r#"
+const LEASE_MS = 30000;
+const entries = new Map();
+const leases = new Map();
+const ports = [];
+
+onconnect = (event) => {
+  const port = event.ports[0];
+  ports.push(port);
+  const broadcast = (message) => {
+    for (const other of ports) if (other !== port) other.postMessage(message);
+  };
+
+  port.onmessage = ({ data }) => {
+    const { type, key } = data;
+    if (type === "get") {
+      if (entries.has(key)) port.postMessage(entries.get(key));
+    } else if (type === "lease") {
+      const taken = leases.get(key);
+      const granted = taken === undefined || Date.now() - taken > LEASE_MS;
+      if (granted) leases.set(key, Date.now());
+      port.postMessage({ type: "lease", id: data.id, granted });
+    } else if (type === "set") {
+      leases.delete(key);
+      entries.set(key, data);
+      broadcast(data);
+    } else if (type === "failed") {
+      leases.delete(key);
+      broadcast(data);
+    }
+  };
+  port.start();
+};
+"#;
+
+        pub fn new(script_url: &str) -> Self {
+            Self {
+                script_url: script_url.to_string(),
+                port: Rc::new(RefCell::new(None)),
+                leases: Rc::new(RefCell::new(HashMap::new())),
+                waiting: Rc::new(RefCell::new(HashMap::new())),
+                received: Rc::new(RefCell::new(HashSet::new())),
+                next_id: Rc::new(Cell::new(0)),
+                _data: PhantomData,
+            }
+        }
+
+        fn post(&self, message: &js_sys::Object) {
+            if let Some(port) = &*self.port.borrow() {
+                let _ = port.post_message(message);
+            }
+        }
+
+        fn message(kind: &str, query_key: &str) -> js_sys::Object {
+            let message = js_sys::Object::new();
+            set(&message, "type", kind);
+            set(&message, "key", query_key);
+            message
+        }
+    }
+
+    #[cfg(feature = "shared-worker")]
+    fn get(target: &wasm_bindgen::JsValue, key: &str) -> wasm_bindgen::JsValue {
+        js_sys::Reflect::get(target, &key.into()).unwrap_or(wasm_bindgen::JsValue::UNDEFINED)
+    }
+
+    // a promise and the function resolving it
+    #[cfg(feature = "shared-worker")]
+    fn deferred() -> (js_sys::Promise, js_sys::Function) {
+        let mut resolve = None;
+        let promise = js_sys::Promise::new(&mut |res, _| resolve = Some(res));
+        (
+            promise,
+            resolve.expect("`Promise` executor runs synchronously"),
+        )
+    }
+
+    #[cfg(feature = "shared-worker")]
+    impl<TData> QueryPlugin for SharedWorkerCache<TData>
+    where
+        TData: Clone + Serialize + DeserializeOwned + 'static,
+    {
+        fn on_query_added(&self, query: &dyn AnyQuery) {
+            if query.data_type() == TypeId::of::<TData>() {
+                self.post(&Self::message("get", &query.query_key()));
+            }
+        }
+
+        fn intercept_fetch(&self, query_key: &str, data_type: TypeId) -> Option<InterceptedFetch> {
+            if data_type != TypeId::of::<TData>() {
+                return None;
+            }
+            self.port.borrow().as_ref()?;
+
+            let id = self.next_id.get();
+            self.next_id.set(id.wrapping_add(1));
+            let (lease, resolve_lease) = deferred();
+            self.leases.borrow_mut().insert(id, resolve_lease);
+
+            let message = Self::message("lease", query_key);
+            set(&message, "id", id);
+            self.post(&message);
+
+            let query_key = query_key.to_string();
+            let waiting = self.waiting.clone();
+            let received = self.received.clone();
+
+            Some(Box::pin(async move {
+                let granted = JsFuture::from(lease).await.ok()?.as_bool()?;
+                if granted {
+                    // this window fetches, the others wait for its result
+                    return None;
+                }
+
+                let (result, resolve) = deferred();
+                waiting
+                    .borrow_mut()
+                    .entry(query_key.clone())
+                    .or_default()
+                    .push(resolve);
+                let result = JsFuture::from(result).await.ok()?;
+
+                received.borrow_mut().insert(query_key);
+                match get(&result, "type").as_string().as_deref() {
+                    Some("set") => {
+                        serde_json::from_str::<TData>(&get(&result, "data").as_string()?)
+                            .ok()
+                            .map(|data| Ok(Box::new(data) as Box<dyn Any>))
+                    }
+                    _ => Some(Err(get(&result, "error").as_string().unwrap_or_default())),
+                }
+            }))
+        }
+
+        fn on_success(&self, query_key: &str, data: &dyn Any) {
+            let Some(data) = data.downcast_ref::<TData>() else {
+                return;
+            };
+            if self.received.borrow_mut().remove(query_key) {
+                return;
+            }
+            if let Ok(data) = serde_json::to_string(data) {
+                let message = Self::message("set", query_key);
+                set(&message, "data", data);
+                set(&message, "updated_at", crate::now() as f64);
+                self.post(&message);
+            }
+        }
+
+        // errors of queries holding other data types are forwarded as well,
+        // the worker only acts on keys it handed a lease out for
+        fn on_error(&self, query_key: &str, error: &str) {
+            if self.received.borrow_mut().remove(query_key) {
+                return;
+            }
+            let message = Self::message("failed", query_key);
+            set(&message, "error", error);
+            self.post(&message);
+        }
+
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let worker = match web_sys::SharedWorker::new(&self.script_url) {
+                Ok(worker) => worker,
+                // no SharedWorker support, every window keeps its own cache
+                Err(_) => return Box::new(|| ()),
+            };
+            let port = worker.port();
+
+            let on_message = {
+                let client = client.clone();
+                let leases = self.leases.clone();
+                let waiting = self.waiting.clone();
+
+                wasm_bindgen::closure::Closure::wrap(Box::new(
+                    move |event: web_sys::MessageEvent| {
+                        let message = event.data();
+                        let query_key = get(&message, "key").as_string().unwrap_or_default();
+
+                        match get(&message, "type").as_string().as_deref() {
+                            Some("lease") => {
+                                let id = get(&message, "id").as_f64().unwrap_or(-1.0) as u32;
+                                if let Some(resolve) = leases.borrow_mut().remove(&id) {
+                                    let _ = resolve.call1(
+                                        &wasm_bindgen::JsValue::NULL,
+                                        &get(&message, "granted"),
+                                    );
+                                }
+                                return;
+                            }
+                            Some("set") => {
+                                let data = get(&message, "data").as_string();
+                                let updated_at =
+                                    get(&message, "updated_at").as_f64().map(|at| at as i64);
+                                client.seed_query::<TData>(
+                                    query_key.clone(),
+                                    Box::new(move || {
+                                        serde_json::from_str(&data?)
+                                            .ok()
+                                            .map(|data| (data, updated_at))
+                                    }),
+                                );
+                            }
+                            Some("failed") => {}
+                            _ => return,
+                        }
+
+                        let waiters = waiting.borrow_mut().remove(&query_key);
+                        for resolve in waiters.into_iter().flatten() {
+                            let _ = resolve.call1(&wasm_bindgen::JsValue::NULL, &message);
+                        }
+                    },
+                )
+                    as Box<dyn FnMut(web_sys::MessageEvent)>)
+            };
+            port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            port.start();
+
+            for query in client.queries.borrow().values() {
+                if query.data_type() == TypeId::of::<TData>() {
+                    let _ = port.post_message(&Self::message("get", &query.query_key()));
+                }
+            }
+            *self.port.borrow_mut() = Some(port);
+
+            let port = self.port.clone();
+            Box::new(move || {
+                if let Some(port) = port.borrow_mut().take() {
+                    port.set_onmessage(None);
+                    port.close();
+                }
+                std::mem::drop(on_message);
+                std::mem::drop(worker);
+            })
+        }
+    }
+
+    // persists the successful queries holding `TData` to `localStorage`,
+    // each under `{storage_key}:{query_key}`, and restores them, so a reload
+    // shows the cached data instead of `Status::Loading`. register it with
+    // `QueryClient::with_persister`, one per data type, each with its own
+    // `storage_key`. an entry is removed along with its query's data, see
+    // `QueryPlugin::on_query_removed`
+    #[cfg(feature = "persist")]
+    pub struct LocalStoragePersister<TData> {
+        storage_key: String,
+        filter: Rc<dyn Fn(&str) -> bool>,
+        // by `QueryClient::with_persister`, otherwise on mount
+        restored: Cell<bool>,
+        _data: PhantomData<TData>,
+    }
+
+    #[cfg(feature = "persist")]
+    impl<TData> LocalStoragePersister<TData> {
+        pub fn new(storage_key: impl Into<String>) -> Self {
+            Self {
+                storage_key: storage_key.into(),
+                filter: Rc::new(|_| true),
+                restored: Cell::new(false),
+                _data: PhantomData,
+            }
+        }
+
+        // only keys passing `filter` are persisted, e.g. to keep just the
+        // profile and settings and leave out sensitive or huge queries.
+        // entries it rejects are deleted from the storage on restore, so a
+        // stricter filter also drops what was persisted before
+        pub fn filter(mut self, filter: impl Fn(&str) -> bool + 'static) -> Self {
+            self.filter = Rc::new(filter);
+            self
+        }
+
+        fn storage() -> Option<web_sys::Storage> {
+            window()?.local_storage().ok().flatten()
+        }
+
+        // where the entry of `query_key` is stored
+        fn item_key(&self, query_key: &str) -> String {
+            format!("{}:{}", self.storage_key, query_key)
+        }
+
+        fn load(&self) -> Vec<DehydratedQuery<serde_json::Value>> {
+            let Some(storage) = Self::storage() else {
+                return vec![];
+            };
+            let prefix = self.item_key("");
+            let item_keys = (0..storage.length().unwrap_or(0))
+                .filter_map(|index| storage.key(index).ok().flatten())
+                .filter(|item_key| item_key.starts_with(&prefix))
+                .collect::<Vec<_>>();
+
+            let mut queries = vec![];
+            for item_key in item_keys {
+                if !(self.filter)(&item_key[prefix.len()..]) {
+                    let _ = storage.remove_item(&item_key);
+                    continue;
+                }
+                let query = storage
+                    .get_item(&item_key)
+                    .ok()
+                    .flatten()
+                    .and_then(|json| serde_json::from_str(&json).ok());
+                queries.extend(query);
+            }
+            queries
+        }
+
+        // the persisted data counts as updated when it was fetched, stale
+        // queries are still refetched once they mount
+        fn restore(&self, client: &QueryClient)
+        where
+            TData: Clone + DeserializeOwned + 'static,
+        {
+            if self.restored.replace(true) {
+                return;
+            }
+            for query in self.load() {
+                let DehydratedQuery {
+                    query_key,
+                    data,
+                    updated_at,
+                } = query;
+
+                client.seed_query::<TData>(
+                    query_key,
+                    Box::new(move || {
+                        serde_json::from_value(data)
+                            .ok()
+                            .map(|data| (data, updated_at))
+                    }),
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "persist")]
+    impl<TData> QueryPlugin for LocalStoragePersister<TData>
+    where
+        TData: Clone + Serialize + DeserializeOwned + 'static,
+    {
+        fn on_success(&self, query_key: &str, data: &dyn Any) {
+            let Some(data) = data.downcast_ref::<TData>() else {
+                return;
+            };
+            if !(self.filter)(query_key) {
+                return;
+            }
+            let (Some(storage), Ok(data)) = (Self::storage(), serde_json::to_value(data)) else {
+                return;
+            };
+            let query = DehydratedQuery {
+                query_key: query_key.to_string(),
+                data,
+                updated_at: Some(crate::now()),
+            };
+            if let Ok(json) = serde_json::to_string(&query) {
+                // e.g. over the quota, the query just isn't persisted
+                let _ = storage.set_item(&self.item_key(query_key), &json);
+            }
+        }
+
+        fn on_query_removed(&self, query_key: &str) {
+            if let Some(storage) = Self::storage() {
+                let _ = storage.remove_item(&self.item_key(query_key));
+            }
+        }
+
+        // only needed if it wasn't registered with `with_persister`, the
+        // queries of the first render start out `Loading` then
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            self.restore(client);
+            Box::new(|| ())
+        }
+    }
+
+    #[cfg(feature = "persist")]
+    impl QueryClient {
+        // restores what `persister` saved right away, so when called before
+        // the first render `use_query` starts out with the persisted data
+        // and stale queries revalidate once they mount
+        pub fn with_persister<TData>(self, persister: LocalStoragePersister<TData>) -> Self
+        where
+            TData: Clone + Serialize + DeserializeOwned + 'static,
+        {
+            persister.restore(&self);
+            self.with_plugin(persister)
+        }
+    }
+
+    // keeps the mutations run by `mutation_key` that paused while offline in
+    // `localStorage`, and reruns them once the next page load mounts the
+    // client, so they survive a reload. rerunning needs the mutation
+    // function, so only keys registered with `set_mutation_defaults` resume
+    #[cfg(feature = "persist")]
+    pub struct PausedMutationPersister<TVariables, TData, TError> {
+        mutation_key: String,
+        storage_key: String,
+        // the stored mutations are only resumed by the first mount
+        resumed: Cell<bool>,
+        _types: PhantomData<(TVariables, TData, TError)>,
+    }
+
+    #[cfg(feature = "persist")]
+    impl<TVariables, TData, TError> PausedMutationPersister<TVariables, TData, TError> {
+        pub fn new(mutation_key: impl Into<String>, storage_key: impl Into<String>) -> Self {
+            Self {
+                mutation_key: mutation_key.into(),
+                storage_key: storage_key.into(),
+                resumed: Cell::new(false),
+                _types: PhantomData,
             }
         }
     }
 
-    pub fn create_query_observer<TData>(
-        client: &mut QueryClient<TData>,
-        options: QueryOptions<TData>,
-    ) -> Subscriber<TData>
+    #[cfg(feature = "persist")]
+    impl<TVariables, TData, TError> QueryPlugin for PausedMutationPersister<TVariables, TData, TError>
     where
-        TData: Clone + PartialEq + Debug,
+        TVariables: Clone + Serialize + DeserializeOwned + 'static,
+        TData: Clone + 'static,
+        TError: Clone + 'static,
     {
-        let query = client.get_query(&options);
-        // web_sys::console::log_1(&format!("{:#?}", query).into());
-        Subscriber {
-            query,
-            stale_time: options.stale_time,
-            cache_time: options.cache_time,
-        }
-    }
-}
-
-pub use utils::{Query, QueryClient, QueryState, Status};
-use wasm_bindgen::JsCast;
-use web_sys::window;
-use yew::{
-    function_component, html, use_context, use_effect_with_deps, use_mut_ref, use_state, Callback,
-    Children, ContextProvider, Properties,
-};
-
-#[derive(Default)]
-pub struct QueryOptions {
-    pub stale_time: Option<i64>,
-    pub cache_time: Option<i32>,
-}
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let Some(storage) = window().and_then(|window| window.local_storage().ok().flatten())
+            else {
+                return Box::new(|| ());
+            };
+            let mutation_cache = client.mutation_cache();
 
-const FIX_MINUTES_MS: i32 = 5 * 60 * 1000;
+            let stored = storage
+                .get_item(&self.storage_key)
+                .ok()
+                .flatten()
+                .and_then(|json| serde_json::from_str::<Vec<TVariables>>(&json).ok())
+                .unwrap_or_default();
 
-pub struct MutationState<TData>
-where
-    TData: Clone + PartialEq + Debug,
-{
-    pub status: Status<TData>,
-}
+            let persist = {
+                let mutation_cache = mutation_cache.clone();
+                let mutation_key = self.mutation_key.clone();
+                let storage_key = self.storage_key.clone();
 
-#[derive(Default)]
-pub struct MutationOptions<Rt> {
-    pub on_success: Option<FnPtr<Rc<Rt>, ()>>,
-    pub on_settled: Option<FnPtr<(), ()>>,
-    pub on_error: Option<FnPtr<String, ()>>,
-    // return_type: PhantomData<&'a Rt>,
-}
+                Callback::from(move |_: Mutation| {
+                    let paused = mutation_cache
+                        .mutations()
+                        .into_iter()
+                        .filter(|mutation| {
+                            mutation.status == MutationStatusKind::Paused
+                                && mutation.mutation_key.as_deref() == Some(&*mutation_key)
+                        })
+                        .filter_map(|mutation| mutation.variables.get::<TVariables>().cloned())
+                        .collect::<Vec<_>>();
+                    if let Ok(json) = serde_json::to_string(&paused) {
+                        let _ = storage.set_item(&storage_key, &json);
+                    }
+                })
+            };
+            mutation_cache.subscribe(persist.clone());
 
-type MutationResult<Arg, Rt> = (Box<CB<Arg, Result<Rt, String>>>, MutationState<Rt>);
+            let defaults =
+                client.get_mutation_defaults::<TVariables, TData, TError>(&self.mutation_key);
+            if let (Some(defaults), false) = (defaults, self.resumed.replace(true)) {
+                let options = Rc::new(defaults.resolve(client, &self.mutation_key));
+                // each of them pauses (and is persisted) again if still offline
+                for variables in stored {
+                    let mutation = crate::run_mutation(
+                        defaults.mutation_fn.clone(),
+                        options.clone(),
+                        Some(mutation_cache.clone()),
+                        variables,
+                        |_| (),
+                    );
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let _ = mutation.await;
+                    });
+                }
+            }
 
-// change the API to builder pattern maybe?
-pub fn use_mutation<Arg, Rt, Cb>(func: Cb, options: MutationOptions<Rt>) -> MutationResult<Arg, Rt>
-where
-    Arg: 'static,
-    Rt: Clone + PartialEq + Debug + 'static,
-    Cb: 'static + Fn(Arg) -> Pin<Box<dyn Future<Output = Result<Rt, String>>>>,
-{
-    let ptr = FnPtr::from(func);
-    let options = Rc::new(options);
-    let mutate = {
-        Box::new(move |arg: Arg| {
-            let ptr = ptr.clone();
-            let options = options.clone();
+            Box::new(move || mutation_cache.unsubscribe(persist))
+        }
+    }
 
-            Box::pin(async move {
-                let result = ptr.emit(arg).await;
+    // messages the bridge posts to the page carry this `source`. commands
+    // sent to it have to use `DevtoolsBridge::COMMAND_SOURCE`
+    #[cfg(feature = "devtools-bridge")]
+    pub const BRIDGE_SOURCE: &str = "yew-query";
 
-                macro_rules! call {
-                    ($func:ident, $value:expr) => {
-                        if let Some($func) = &options.$func {
-                            $func.emit($value).await
-                        }
-                    };
-                }
+    // lets a browser extension inspect and drive the cache over
+    // `window.postMessage`:
+    //
+    // - `{ source, type: "snapshot", queries: [{ key, data_type, status,
+    //   fetch_status, is_stale, last_updated, subscribers }] }` on mount,
+    //   whenever the cache changes and on request
+    // - `{ source, type: "event", event, query_key, error? }` for fetches,
+    //   successes, errors, added and garbage collected queries
+    //
+    // and accepts `{ source: COMMAND_SOURCE, command, query_key?, exact? }`
+    // with `command` being "snapshot", "invalidate" or "refetch" (by prefix
+    // unless `exact`). commands are only taken from the page's own window
+    // and origin, where the extension's content script posts from, so
+    // iframes and other windows can't drive the cache
+    #[cfg(feature = "devtools-bridge")]
+    pub struct DevtoolsBridge;
 
-                match &result {
-                    Ok(result) => {
-                        let result_rc = Rc::new(result.clone());
-                        call!(on_success, result_rc.clone())
-                    }
-                    Err(err) => {
-                        call!(on_error, err.clone());
-                    }
-                };
+    #[cfg(feature = "devtools-bridge")]
+    impl DevtoolsBridge {
+        pub const COMMAND_SOURCE: &'static str = "yew-query-devtools";
+    }
 
-                call!(on_settled, ());
+    #[cfg(any(feature = "devtools-bridge", feature = "shared-worker"))]
+    fn set(target: &js_sys::Object, key: &str, value: impl Into<wasm_bindgen::JsValue>) {
+        js_sys::Reflect::set(target, &key.into(), &value.into()).expect("Couldn't set property");
+    }
 
-                result
-            }) as Pin<Box<dyn Future<Output = Result<Rt, String>>>>
-        })
-    };
+    #[cfg(feature = "devtools-bridge")]
+    fn get_string(target: &wasm_bindgen::JsValue, key: &str) -> Option<String> {
+        js_sys::Reflect::get(target, &key.into())
+            .ok()
+            .and_then(|value| value.as_string())
+    }
 
-    (
-        mutate,
-        MutationState {
-            status: Status::Idle,
-        },
-    )
-}
+    #[cfg(feature = "devtools-bridge")]
+    fn post(message: &js_sys::Object) {
+        let window = window().expect("Couldn't access `window`");
+        let origin = window.location().origin().unwrap_or_else(|_| "*".into());
+        let _ = window.post_message(message, &origin);
+    }
 
-pub fn use_query<TData, F>(
-    query_key: &str,
-    query_fn: F,
-    options: QueryOptions,
-) -> utils::QueryState<TData>
-where
-    TData: Clone + PartialEq + Debug + 'static,
-    F: 'static + Fn(()) -> Pin<Box<dyn Future<Output = Result<TData, String>>>>,
-{
-    let query_fn = FnPtr::from(query_fn);
-    let mut client = use_query_client::<TData>();
+    #[cfg(feature = "devtools-bridge")]
+    fn post_event(event: &str, query_key: &str, error: Option<&str>) {
+        let message = js_sys::Object::new();
+        set(&message, "source", BRIDGE_SOURCE);
+        set(&message, "type", "event");
+        set(&message, "event", event);
+        set(&message, "query_key", query_key);
+        if let Some(error) = error {
+            set(&message, "error", error);
+        }
+        post(&message);
+    }
 
-    let rerender = {
-        let c = use_state(|| 0);
-        move || {
-            c.set(*c + 1);
+    #[cfg(feature = "devtools-bridge")]
+    fn post_snapshot(client: &QueryClient) {
+        let queries = js_sys::Array::new();
+        for query in client.queries.borrow().values() {
+            let status = query.status();
+            let entry = js_sys::Object::new();
+            set(&entry, "key", query.query_key());
+            set(&entry, "data_type", query.data_type_name());
+            set(
+                &entry,
+                "status",
+                match status.status {
+                    QueryStatus::Idle => "idle",
+                    QueryStatus::Loading => "loading",
+                    QueryStatus::Success => "success",
+                    QueryStatus::Error => "error",
+                },
+            );
+            set(
+                &entry,
+                "fetch_status",
+                match status.fetch_status {
+                    FetchStatus::Idle => "idle",
+                    FetchStatus::Fetching => "fetching",
+                    FetchStatus::Paused => "paused",
+                },
+            );
+            set(&entry, "is_stale", query.is_stale());
+            set(
+                &entry,
+                "last_updated",
+                query
+                    .last_updated()
+                    .map(|at| wasm_bindgen::JsValue::from_f64(at as f64))
+                    .unwrap_or(wasm_bindgen::JsValue::NULL),
+            );
+            set(&entry, "subscribers", query.subscriber_count() as u32);
+            queries.push(&entry);
         }
-    };
-    let observer_ref = use_mut_ref(|| {
-        web_sys::console::log_1(&"created query observer".into());
 
-        utils::create_query_observer(
-            &mut client,
-            utils::QueryOptions {
-                query_fn,
-                query_key: String::from(query_key),
-                stale_time: options.stale_time.unwrap_or(0),
-                cache_time: options.cache_time.unwrap_or(FIX_MINUTES_MS),
-            },
-        )
-    });
+        let message = js_sys::Object::new();
+        set(&message, "source", BRIDGE_SOURCE);
+        set(&message, "type", "snapshot");
+        set(&message, "queries", queries);
+        post(&message);
+    }
 
-    {
-        let observer_ref = observer_ref.clone();
+    #[cfg(feature = "devtools-bridge")]
+    impl QueryPlugin for DevtoolsBridge {
+        fn on_query_added(&self, query: &dyn AnyQuery) {
+            post_event("query_added", &query.query_key(), None);
+        }
 
-        use_effect_with_deps(
-            move |_| {
-                web_sys::console::log_1(&"rerender".into());
+        fn on_fetch(&self, query_key: &str) {
+            post_event("fetch", query_key, None);
+        }
 
-                let cb = Callback::<()>::from(move |_| rerender());
-                let mut observer = observer_ref.borrow_mut();
-                observer.subscribe(cb.clone());
+        fn on_success(&self, query_key: &str, _data: &dyn std::any::Any) {
+            post_event("success", query_key, None);
+        }
 
-                {
-                    let mut observer = observer.clone();
-                    move || observer.unsubscribe(cb.clone())
-                }
-            },
-            (),
-        );
-    }
+        fn on_error(&self, query_key: &str, error: &str) {
+            post_event("error", query_key, Some(error));
+        }
 
-    let result = observer_ref.borrow_mut().get_result();
-    result
-}
+        fn on_gc(&self, query_key: &str) {
+            post_event("gc", query_key, None);
+        }
 
-#[derive(Properties, PartialEq)]
-pub struct QueryClientProviderProps<T>
-where
-    T: Clone + Debug + PartialEq + 'static,
-{
-    pub client: QueryClient<T>,
-    #[prop_or_default]
-    pub children: Children,
-}
+        fn on_mount(&self, client: &QueryClient) -> Box<dyn FnOnce()> {
+            let mut client = client.clone();
 
-pub fn use_query_client<TData>() -> QueryClient<TData>
-where
-    TData: Clone + PartialEq + Debug + 'static,
-{
-    use_context::<QueryClient<TData>>().expect("QueryContext not found")
-}
+            let on_change = {
+                let client = client.clone();
+                Callback::from(move |_: ()| {
+                    let client = client.clone();
+                    // the notifying query is still borrowed at this point
+                    wasm_bindgen_futures::spawn_local(async move { post_snapshot(&client) });
+                })
+            };
+            client.subscribe(on_change.clone());
 
-#[function_component(QueryClientProvider)]
-pub fn query_client_provider<T>(props: &QueryClientProviderProps<T>) -> Html
-where
-    T: Clone + Debug + PartialEq + 'static,
-{
-    let client = props.client.clone();
+            let on_message = {
+                let client = client.clone();
+                let window = window().expect("Couldn't access `window`");
+                let origin = window.location().origin().ok();
+                let page = wasm_bindgen::JsValue::from(window);
+                wasm_bindgen::closure::Closure::wrap(Box::new(
+                    move |event: web_sys::MessageEvent| {
+                        let from_page = event
+                            .source()
+                            .is_some_and(|source| wasm_bindgen::JsValue::from(source) == page);
+                        if !from_page || Some(event.origin()) != origin {
+                            return;
+                        }
+                        let data = event.data();
+                        if get_string(&data, "source").as_deref()
+                            != Some(DevtoolsBridge::COMMAND_SOURCE)
+                        {
+                            return;
+                        }
 
-    {
-        let queries = client.queries.clone();
+                        let query_key = get_string(&data, "query_key").unwrap_or_default();
+                        let exact = js_sys::Reflect::get(&data, &"exact".into())
+                            .ok()
+                            .and_then(|exact| exact.as_bool())
+                            .unwrap_or(false);
+                        let filters = if exact {
+                            QueryFilters::exact(query_key)
+                        } else {
+                            QueryFilters::prefix(query_key)
+                        };
+                        let client = client.clone();
 
-        use_effect_with_deps(
-            move |_| {
-                let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
-                    for query in (queries).borrow_mut().iter() {
-                        for (subscriber, _) in &mut query.borrow_mut().subscribers {
-                            subscriber.fetch()
+                        match get_string(&data, "command").as_deref() {
+                            Some("snapshot") => post_snapshot(&client),
+                            Some("invalidate") => wasm_bindgen_futures::spawn_local(async move {
+                                client.invalidate_queries(filters).await
+                            }),
+                            Some("refetch") => wasm_bindgen_futures::spawn_local(async move {
+                                client.refetch_queries(filters).await
+                            }),
+                            _ => {}
                         }
-                    }
-                })
-                    as Box<dyn FnMut()>);
-                let on_focus = closure.as_ref().unchecked_ref::<js_sys::Function>();
+                    },
+                )
+                    as Box<dyn FnMut(web_sys::MessageEvent)>)
+            };
+            let listener = on_message
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone();
 
-                let window = window().expect("Couldn't access `window`");
+            let window = window().expect("Couldn't access `window`");
+            window
+                .add_event_listener_with_callback("message", &listener)
+                .unwrap();
+            post_snapshot(&client);
 
+            Box::new(move || {
                 window
-                    .add_event_listener_with_callback_and_bool(
-                        "visibilitychange",
-                        &on_focus.clone(),
-                        false,
-                    )
-                    .unwrap();
-                window
-                    .add_event_listener_with_callback_and_bool("focus", &on_focus.clone(), false)
+                    .remove_event_listener_with_callback("message", &listener)
                     .unwrap();
+                client.unsubscribe(on_change);
+                std::mem::drop(on_message);
+            })
+        }
+    }
+}
 
-                {
-                    let on_focus = on_focus.clone();
+#[cfg(feature = "devtools")]
+pub mod devtools {
+    use crate::{
+        use_query_client,
+        utils::{AnyQuery, FetchStatus, QueryStatus, QueryStatusState, RefetchTriggers},
+        QueryKey,
+    };
+    use std::rc::Rc;
+    use yew::{
+        function_component, html, use_effect_with_deps, use_state, Callback, Html, Properties,
+    };
 
-                    move || {
-                        window
-                            .remove_event_listener_with_callback(
-                                "visibilitychange",
-                                &on_focus.clone(),
-                            )
-                            .unwrap();
-                        window
-                            .remove_event_listener_with_callback("focus", &on_focus.clone())
-                            .unwrap()
-                    }
-                }
-            },
-            client,
-        );
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum Health {
+        Fresh,
+        Stale,
+        Fetching,
+        Inactive,
+        Error,
     }
 
-    html! {
-        <ContextProvider<QueryClient<T>> context={props.client.clone()}>
-            { for props.children.iter() }
-        </ContextProvider<QueryClient<T>>>
+    impl Health {
+        const ALL: [Health; 5] = [
+            Health::Fresh,
+            Health::Stale,
+            Health::Fetching,
+            Health::Inactive,
+            Health::Error,
+        ];
+
+        fn of(query: &dyn AnyQuery) -> Self {
+            let status = query.status();
+            if status.fetch_status != FetchStatus::Idle {
+                Health::Fetching
+            } else if query.subscriber_count() == 0 {
+                Health::Inactive
+            } else if status.status == QueryStatus::Error {
+                Health::Error
+            } else if query.is_stale() {
+                Health::Stale
+            } else {
+                Health::Fresh
+            }
+        }
+
+        pub fn label(self) -> &'static str {
+            match self {
+                Health::Fresh => "fresh",
+                Health::Stale => "stale",
+                Health::Fetching => "fetching",
+                Health::Inactive => "inactive",
+                Health::Error => "error",
+            }
+        }
     }
-}
 
-// #[cfg(feature = "devtools")]
-pub mod devtools {
-    use crate::{use_query_client, utils::Status};
-    use yew::{function_component, html, use_effect_with_deps, use_state, Callback};
+    // colors used by the devtools panel
+    #[derive(Clone, PartialEq, Debug, Default)]
+    pub enum DevtoolsTheme {
+        #[default]
+        Dark,
+        Light,
+        Custom {
+            background: String,
+            foreground: String,
+            accent: String,
+        },
+    }
 
-    #[function_component(QueryDevtools)]
-    pub fn query_devtools<TData>() -> Html
+    impl DevtoolsTheme {
+        // (background, foreground, accent)
+        fn colors(&self) -> (&str, &str, &str) {
+            match self {
+                DevtoolsTheme::Dark => ("black", "white", "#f0a500"),
+                DevtoolsTheme::Light => ("white", "black", "#0b63c5"),
+                DevtoolsTheme::Custom {
+                    background,
+                    foreground,
+                    accent,
+                } => (background, foreground, accent),
+            }
+        }
+    }
+
+    // what a render slot gets to work with for a single query. the data
+    // itself can be read with `QueryClient::get_query_data`
+    #[derive(Clone, PartialEq, Debug)]
+    pub struct DevtoolsRow {
+        pub query_key: String,
+        pub data_type: &'static str,
+        pub health: Health,
+        pub status: QueryStatusState,
+        pub error: Option<String>,
+        pub last_updated: Option<i64>,
+        pub subscribers: usize,
+        // combined over every subscriber
+        pub refetch_triggers: RefetchTriggers,
+    }
+
+    // `Callback` can't return anything in yew 0.19, so render slots get
+    // their own wrapper, compared by pointer like `FnPtr`
+    pub struct RenderFn<T>(Rc<dyn Fn(T) -> Html>);
+
+    impl<T> RenderFn<T> {
+        fn render(&self, arg: T) -> Html {
+            (self.0)(arg)
+        }
+    }
+
+    impl<T, F> From<F> for RenderFn<T>
     where
-        TData: Clone + PartialEq + std::fmt::Debug + 'static,
+        F: Fn(T) -> Html + 'static,
     {
-        let client = use_query_client::<TData>();
+        fn from(f: F) -> Self {
+            Self(Rc::new(f))
+        }
+    }
+
+    impl<T> Clone for RenderFn<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<T> PartialEq for RenderFn<T> {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.0, &other.0)
+        }
+    }
+
+    #[derive(Properties, PartialEq)]
+    pub struct QueryDevtoolsProps {
+        #[prop_or_default]
+        pub theme: DevtoolsTheme,
+        // replaces the default `"key" - health` row
+        #[prop_or_default]
+        pub render_row: Option<RenderFn<DevtoolsRow>>,
+        // replaces the default panel shown for the selected query
+        #[prop_or_default]
+        pub render_detail: Option<RenderFn<DevtoolsRow>>,
+        // only lists the queries below this key in its hierarchy
+        #[prop_or_default]
+        pub key_prefix: Option<QueryKey>,
+    }
+
+    #[function_component(QueryDevtools)]
+    pub fn query_devtools(props: &QueryDevtoolsProps) -> Html {
+        let client = use_query_client();
+        let filter = use_state(|| Option::<Health>::None);
+        let selected = use_state(|| Option::<String>::None);
         let rerender = {
             let c = use_state(|| 0);
             Callback::from(move |_: ()| {
                 c.set(*c + 1);
             })
         };
-        let mut queries = {
-            let queries = (*client.queries).clone();
-
-            queries
-                .borrow_mut()
-                .sort_by_cached_key(|query| (*query).borrow().query_key.clone());
+        let (background, foreground, accent) = props.theme.colors();
+        // ordered by the hash, which lists structured keys next to their
+        // parents
+        let queries = client
+            .queries
+            .borrow()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        let queries = queries
+            .iter()
+            .filter(|query| {
+                props
+                    .key_prefix
+                    .as_ref()
+                    .is_none_or(|prefix| prefix.is_prefix_of(&query.query_key()))
+            })
+            .map(|query| DevtoolsRow {
+                query_key: query.query_key(),
+                data_type: query.data_type_name(),
+                health: Health::of(&**query),
+                status: query.status(),
+                error: query.error(),
+                last_updated: query.last_updated(),
+                subscribers: query.subscriber_count(),
+                refetch_triggers: query.refetch_triggers(),
+            })
+            .collect::<Vec<_>>();
 
-            queries
-        };
-        let queries = queries.get_mut().iter().map(|query| {
-            let query = (**query).borrow();
+        let counters = Health::ALL.iter().map(|&health| {
+            let count = queries.iter().filter(|row| row.health == health).count();
+            let active = *filter == Some(health);
+            let onclick = {
+                let filter = filter.clone();
+                // clicking the selected counter again clears the filter
+                Callback::from(move |_| filter.set(if active { None } else { Some(health) }))
+            };
+            let style = if active {
+                format!("cursor: pointer; margin-right: 8px; color: {};", accent)
+            } else {
+                "cursor: pointer; margin-right: 8px;".to_string()
+            };
 
             html! {
-                <div style="">
-                    { format!("\"{}\" -", query.query_key.clone()) }
-                    <span style="">
-                        { if query.state.is_fetching {
-                            html! { <span style="">{ "fetching" }</span> }
-                        } else if query.subscribers.is_empty() {
-                            html! { <span style="">{ "inactive" }</span> }
-                        } else if let Status::Success(_) = query.state.status {
-                            html! { <span style="">{ "success" }</span> }
-                        } else if let Status::Error(_) = query.state.status {
-                            html! { <span style="">{ "error" }</span> }
-                        } else {
-                            html! {}
-                        } }
-                    </span>
-                </div>
+                <span {style} {onclick}>{ format!("{}: {}", health.label(), count) }</span>
             }
         });
 
+        let rows = queries
+            .iter()
+            .filter(|row| filter.is_none_or(|filter| filter == row.health))
+            .map(|row| {
+                let onclick = {
+                    let selected = selected.clone();
+                    let query_key = row.query_key.clone();
+                    Callback::from(move |_| selected.set(Some(query_key.clone())))
+                };
+                let content = match &props.render_row {
+                    Some(render_row) => render_row.render(row.clone()),
+                    None => html! {
+                        <>
+                            { format!("\"{}\" -", row.query_key) }
+                            <span style="">{ row.health.label() }</span>
+                        </>
+                    },
+                };
+
+                html! {
+                    <div style="cursor: pointer;" {onclick}>{ content }</div>
+                }
+            });
+
+        let detail = selected
+            .as_ref()
+            .and_then(|query_key| queries.iter().find(|row| &row.query_key == query_key))
+            .map(|row| match &props.render_detail {
+                Some(render_detail) => render_detail.render(row.clone()),
+                None => html! {
+                    <pre style="margin: 0;">
+                        { format!("{:#?}", row) }
+                    </pre>
+                },
+            });
+
         use_effect_with_deps(
             move |_| {
                 let mut client = client.clone();
@@ -692,8 +7397,14 @@ pub mod devtools {
         );
 
         html! {
-            <div style="background-color: black; color: white;">
-                {for queries}
+            <div style={format!("background-color: {}; color: {};", background, foreground)}>
+                <div style={format!("border-bottom: 1px solid {};", accent)}>
+                    {for counters}
+                </div>
+                {for rows}
+                if let Some(detail) = detail {
+                    <div style={format!("border-top: 1px solid {};", accent)}>{ detail }</div>
+                }
             </div>
         }
     }
@@ -744,3 +7455,257 @@ macro_rules! query_response {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn str_hash_is_quoted_without_debug_escapes() {
+        assert_eq!("posts".query_key_hash(), r#""posts""#);
+        assert_eq!("café\n".query_key_hash(), "\"café\n\"");
+        assert_eq!(r#"a"b\c"#.query_key_hash(), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn str_parts_with_delimiters_round_trip() {
+        let key = QueryKey::from(("a\",b]", r#"c\"d"#));
+        assert_eq!(QueryKey::parse(&key.hash()), Some(key));
+    }
+
+    #[test]
+    fn char_parts_are_quoted_like_strings() {
+        for c in [',', ']', '"', '1'] {
+            let key = QueryKey::from("posts").push(c);
+            assert_eq!(QueryKey::parse(&key.hash()), Some(key.clone()));
+            assert_eq!(key.parts()[1], c.to_string().query_key_hash());
+        }
+        assert_ne!('1'.query_key_hash(), 1.query_key_hash());
+        let comma = QueryKey::from((',',));
+        assert!(comma.is_prefix_of(&comma.clone().push(1).hash()));
+        assert!(!QueryKey::from(('1',)).is_prefix_of(&QueryKey::from((1, 2)).hash()));
+    }
+
+    #[test]
+    fn query_key_parse_round_trips_its_hash() {
+        let key = QueryKey::from("posts")
+            .push(1)
+            .push(("comments", vec![2, 3]));
+        assert_eq!(key.hash(), r#"["posts",1,["comments",[2,3]]]"#);
+        assert_eq!(QueryKey::parse(&key.hash()), Some(key));
+        assert_eq!(QueryKey::parse("[]"), Some(QueryKey::new()));
+    }
+
+    #[test]
+    fn query_key_parse_rejects_malformed_hashes() {
+        for hash in [
+            "posts",
+            "[",
+            r#"["posts""#,
+            r#"["posts"]]"#,
+            "[1,]",
+            "[,1]",
+            "[[1]",
+        ] {
+            assert_eq!(QueryKey::parse(hash), None, "{}", hash);
+        }
+    }
+
+    #[test]
+    fn query_key_is_prefix_of_matches_whole_parts() {
+        let posts = QueryKey::from("posts");
+        assert!(posts.is_prefix_of(r#"["posts"]"#));
+        assert!(posts.is_prefix_of(r#"["posts",1]"#));
+        assert!(!posts.is_prefix_of(r#"["postsx"]"#));
+        assert!(!posts.is_prefix_of(r#"["post"]"#));
+        assert!(!posts.is_prefix_of("posts"));
+        assert!(!posts.push(1).is_prefix_of(r#"["posts",10]"#));
+        assert!(QueryKey::new().is_prefix_of(r#"["posts"]"#));
+    }
+
+    // a disabled query, cached without fetching or subscribing to it
+    fn cached_query(client: &mut QueryClient, query_key: &str) -> Rc<RefCell<Query<i32>>> {
+        let query_fn = FnPtr::from(
+            |_: QueryFunctionContext| -> Pin<Box<dyn Future<Output = QueryResult<i32>>>> {
+                Box::pin(async { Ok(1) })
+            },
+        );
+        let options = QueryOptions {
+            enabled: false,
+            ..QueryOptions::default()
+        };
+        client.get_query(&options.resolve(client, query_key, query_fn))
+    }
+
+    #[test]
+    fn query_filters_match_on_the_key() {
+        let mut client = QueryClient::new();
+        let key = QueryKey::from("posts").push(1).hash();
+        let query = cached_query(&mut client, &key);
+        let query = &*query as &dyn AnyQuery;
+
+        assert!(QueryFilters::all().matches(query));
+        assert!(QueryFilters::exact(key.as_str()).matches(query));
+        assert!(!QueryFilters::exact(r#"["posts"]"#).matches(query));
+        assert!(QueryFilters::prefix(r#"["po"#).matches(query));
+        assert!(!QueryFilters::prefix(r#"["todos"#).matches(query));
+        assert!(QueryFilters::key(QueryKey::from("posts")).matches(query));
+        assert!(!QueryFilters::key(QueryKey::from("post")).matches(query));
+        assert!(QueryFilters::predicate(|query_key| query_key.ends_with("1]")).matches(query));
+        assert!(!QueryFilters::predicate(|_| false).matches(query));
+    }
+
+    #[test]
+    fn query_filters_match_on_the_state() {
+        let mut client = QueryClient::new();
+        let cached = cached_query(&mut client, "posts");
+        let query = &*cached as &dyn AnyQuery;
+
+        // without subscribers it's inactive, and so stale
+        assert!(QueryFilters::all().stale(true).matches(query));
+        assert!(!QueryFilters::all().stale(false).matches(query));
+        assert!(QueryFilters::all().active(false).matches(query));
+        assert!(!QueryFilters::all().active(true).matches(query));
+        assert!(QueryFilters::all().fetching(false).matches(query));
+
+        cached.borrow_mut().state.fetch_status = FetchStatus::Fetching;
+        assert!(QueryFilters::all().fetching(true).matches(query));
+        // every condition has to hold
+        assert!(!QueryFilters::exact("posts").fetching(false).matches(query));
+        assert!(QueryFilters::exact("posts")
+            .fetching(true)
+            .stale(true)
+            .matches(query));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_with(1000, 0, 0.0), 1000);
+        assert_eq!(backoff_with(1000, 3, 0.0), 8000);
+        assert_eq!(backoff_with(1000, 10, 0.0), MAX_RETRY_DELAY_MS);
+        assert_eq!(backoff_with(i32::MAX, 40, 0.0), MAX_RETRY_DELAY_MS);
+        assert_eq!(backoff_with(0, 5, 0.5), 0);
+    }
+
+    #[test]
+    fn backoff_jitter_adds_up_to_a_quarter() {
+        assert_eq!(backoff_with(1000, 0, 0.5), 1125);
+        assert!(backoff_with(1000, 0, 0.999_999) < 1250);
+        assert!(backoff_with(1000, 10, 0.999_999) < MAX_RETRY_DELAY_MS + MAX_RETRY_DELAY_MS / 4);
+    }
+
+    #[test]
+    fn retry_delay_fn_gets_the_failures_and_error() {
+        let delay = RetryDelay::Fn(Rc::new(|failures, error: &str| {
+            Duration::from_millis(u64::from(failures) * error.len() as u64)
+        }));
+        assert_eq!(delay.delay(2, "boom"), 8);
+        // saturates at what timers take
+        let delay = RetryDelay::Fn(Rc::new(|_, _: &str| Duration::MAX));
+        assert_eq!(delay.delay(0, "boom"), i32::MAX);
+    }
+
+    #[test]
+    fn retry_counts_failures_or_asks_the_predicate() {
+        let retry = Retry::Count(2);
+        assert!(retry.should_retry(0, "boom"));
+        assert!(retry.should_retry(1, "boom"));
+        assert!(!retry.should_retry(2, "boom"));
+        assert!(!Retry::default().should_retry(0, "boom"));
+
+        let retry = Retry::If(Rc::new(|failures, error: &str| {
+            failures < 5 && error != "404"
+        }));
+        assert!(retry.should_retry(4, "500"));
+        assert!(!retry.should_retry(5, "500"));
+        assert!(!retry.should_retry(0, "404"));
+    }
+
+    #[test]
+    fn stale_time_millis_round_trip() {
+        let stale_time = StaleTime::from(Duration::from_secs(5));
+        assert_eq!(stale_time.as_millis(), 5000);
+        assert_eq!(StaleTime::from_millis(5000), stale_time);
+        assert_eq!(StaleTime::Infinite.as_millis(), i64::MAX);
+        assert_eq!(StaleTime::from_millis(i64::MAX), StaleTime::Infinite);
+        // too long to count in ms is as good as never
+        assert_eq!(StaleTime::After(Duration::MAX).as_millis(), i64::MAX);
+        assert_eq!(StaleTime::from_millis(-1), StaleTime::After(Duration::ZERO));
+    }
+
+    #[test]
+    fn gc_time_millis_round_trip() {
+        let gc_time = GcTime::from(Duration::from_secs(5));
+        assert_eq!(gc_time.as_millis(), Some(5000));
+        assert_eq!(GcTime::from_millis(Some(5000)), gc_time);
+        assert_eq!(GcTime::Infinite.as_millis(), None);
+        assert_eq!(GcTime::from_millis(None), GcTime::Infinite);
+        assert_eq!(GcTime::After(Duration::MAX).as_millis(), Some(i32::MAX));
+        assert_eq!(GcTime::from_millis(Some(-1)), GcTime::After(Duration::ZERO));
+    }
+
+    fn success(data: &Rc<i32>) -> QueryState<i32> {
+        QueryState {
+            status: Status::Success(Rc::clone(data)),
+            fetch_status: FetchStatus::Idle,
+            is_previous_data: false,
+            last_updated: Some(1),
+            is_invalidated: false,
+            is_placeholder_data: false,
+            is_stale: false,
+        }
+    }
+
+    #[test]
+    fn notify_on_change_props_compare_data_by_rc() {
+        let data = Rc::new(1);
+        let old = success(&data);
+        let props = NotifyOnChangeProps {
+            data: true,
+            ..NotifyOnChangeProps::NONE
+        };
+
+        assert!(!props.changed(&old, &success(&data)));
+        // equal data refetched into a new `Rc`
+        assert!(props.changed(&old, &success(&Rc::new(1))));
+        let loading = QueryState {
+            status: Status::Loading,
+            ..success(&data)
+        };
+        assert!(props.changed(&old, &loading));
+    }
+
+    #[test]
+    fn notify_on_change_props_only_compare_what_is_tracked() {
+        let data = Rc::new(1);
+        let old = success(&data);
+        let paused = QueryState {
+            fetch_status: FetchStatus::Paused,
+            ..success(&data)
+        };
+        let failed = QueryState {
+            status: Status::Error(String::from("boom")),
+            ..success(&data)
+        };
+
+        assert!(NotifyOnChangeProps::ALL.changed(&old, &paused));
+        assert!(!NotifyOnChangeProps::NONE.changed(&old, &paused));
+        let data_only = NotifyOnChangeProps {
+            data: true,
+            ..NotifyOnChangeProps::NONE
+        };
+        assert!(!data_only.changed(&old, &paused));
+        let fetch_status_only = NotifyOnChangeProps {
+            fetch_status: true,
+            ..NotifyOnChangeProps::NONE
+        };
+        assert!(fetch_status_only.changed(&old, &paused));
+        let error_only = NotifyOnChangeProps {
+            error: true,
+            ..NotifyOnChangeProps::NONE
+        };
+        assert!(error_only.changed(&old, &failed));
+        assert!(!error_only.changed(&failed, &failed.clone()));
+        assert!(!error_only.changed(&old, &paused));
+    }
+}